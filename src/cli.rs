@@ -1,3 +1,4 @@
+use chrono::NaiveDate;
 use clap::{Args, Parser, Subcommand};
 
 #[derive(Debug, Parser)]
@@ -26,6 +27,9 @@ pub enum Command {
 
     #[clap(about = "Install a release.")]
     Install(Install),
+
+    #[clap(about = "Remove an installed release.")]
+    Remove(Remove),
 }
 
 #[derive(Debug, Args)]
@@ -34,13 +38,32 @@ pub struct List {
     #[clap(help = "List only the releases installed in the install directory.")]
     pub installed: bool,
 
+    #[arg(long, default_value = "false", requires = "installed")]
+    #[clap(help = "Fetch releases to recognize installed tools that are not in the cache.")]
+    pub fetch: bool,
+
+    #[arg(short, long)]
+    #[clap(help = "The number of releases to list. Defaults to 10.")]
+    pub count: Option<usize>,
+
+    #[arg(short, long, default_value = "false", conflicts_with = "count")]
+    #[clap(help = "List every release.")]
+    pub all: bool,
+
     #[arg(short, long)]
-    #[clap(help = "The number of releases to list.")]
-    pub count: Option<u8>,
+    #[clap(help = "The page of releases to list, in pages of --count releases.")]
+    pub page: Option<u32>,
+
+    #[arg(long)]
+    #[clap(help = "List only releases published on or after this date (YYYY-MM-DD).")]
+    pub since: Option<NaiveDate>,
 }
 
 #[derive(Debug, Args)]
 pub struct Install {
+    #[clap(
+        help = "The release to install: a tag, \"latest\", \"previous\", a prefix such as GE-Proton9, a pattern such as GE-Proton9-* or a comparison such as >=GE-Proton8-25."
+    )]
     pub tag: String,
 
     #[arg(long = "cache", default_value = "true")]
@@ -66,5 +89,18 @@ pub struct Install {
 
 #[derive(Debug, Args)]
 pub struct Remove {
-    tag: String,
+    #[arg(required_unless_present = "all_but_latest")]
+    pub tag: Option<String>,
+
+    #[arg(long = "keep-cache", default_value = "false")]
+    #[clap(help = "Keep the downloaded archive in the cache directory.")]
+    pub keep_cache: bool,
+
+    #[arg(
+        long = "all-but-latest",
+        default_value = "false",
+        conflicts_with = "tag"
+    )]
+    #[clap(help = "Remove every installed release except the latest one.")]
+    pub all_but_latest: bool,
 }
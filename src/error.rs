@@ -1,5 +1,5 @@
 pub enum Error {
-    IoError(std::io::Error),
+    Io(std::io::Error),
     Config(config::ConfigError),
     Url(String),
     Serde(serde_json::Error),
@@ -7,9 +7,37 @@ pub enum Error {
     NotFound(String),
     FileTypeNotSupported(String),
     Unspecified(String),
-    Mismatch { expected: String, actual: String },
+    Mismatch {
+        expected: String,
+        actual: String,
+    },
+    /// An asset's hash differs from the one recorded the first time it was installed.
+    PinMismatch {
+        asset: String,
+        expected: String,
+        actual: String,
+    },
     CacheFileNotFound(String),
     NoDownloadStrategy,
+    AmbiguousAsset(Vec<String>),
+    /// Trusted keys are configured but the release has no signature for the file.
+    SignatureMissing(String),
+    /// The signature does not match the file, or was not made by a trusted key.
+    SignatureInvalid(String),
+    RateLimited {
+        reset: chrono::DateTime<chrono::Utc>,
+    },
+    /// Entries of an archive that could not be extracted safely, each with the reason.
+    ExtractFailed(Vec<String>),
+    /// An archive that extracts to more files or bytes than the configured limits.
+    ArchiveTooLarge(String),
+    /// An extracted tool that lacks the files it needs, such as `compatibilitytool.vdf`.
+    InvalidInstall(Vec<String>),
+    /// A failure that may go away if the request is retried, such as a timeout or a 503.
+    Network {
+        message: String,
+        retry_after: Option<std::time::Duration>,
+    },
 }
 
 impl Error {
@@ -21,7 +49,7 @@ impl Error {
 impl std::fmt::Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::IoError(e) => write!(f, "IO: {}", e),
+            Error::Io(e) => write!(f, "IO: {}", e),
             Error::Config(e) => write!(f, "Config: {}", e),
             Error::Url(_e) => write!(f, "URL Error"),
             Error::Serde(e) => write!(f, "Serde: {}", e),
@@ -35,14 +63,43 @@ impl std::fmt::Debug for Error {
                 "Hash Mismatch Error: expected {}, got {}",
                 expected, actual
             ),
+            Error::PinMismatch {
+                asset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} changed since it was first installed: pinned {}, got {}",
+                asset, expected, actual
+            ),
             Error::NoDownloadStrategy => write!(f, "No download strategy"),
+            Error::AmbiguousAsset(candidates) => write!(
+                f,
+                "Several assets match, set asset_pattern or asset_preference to choose one of: {}",
+                candidates.join(", ")
+            ),
+            Error::SignatureMissing(e) => write!(f, "No signature found for {}", e),
+            Error::SignatureInvalid(e) => write!(f, "Invalid signature: {}", e),
+            Error::RateLimited { reset } => write!(
+                f,
+                "Rate limit exceeded, try again after {}",
+                reset.format("%Y-%m-%d %H:%M:%S UTC")
+            ),
+            Error::ExtractFailed(entries) => {
+                write!(f, "Could not extract {}", entries.join(", "))
+            }
+            Error::ArchiveTooLarge(e) => write!(f, "Archive too large: {}", e),
+            Error::InvalidInstall(missing) => {
+                write!(f, "Invalid install, missing {}", missing.join(", "))
+            }
+            Error::Network { message, .. } => write!(f, "Network: {}", message),
         }
     }
 }
 
 impl std::fmt::Display for Error {
-    fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        todo!()
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self, f)
     }
 }
 
@@ -50,7 +107,7 @@ impl std::error::Error for Error {}
 
 impl From<std::io::Error> for Error {
     fn from(error: std::io::Error) -> Self {
-        Error::IoError(error)
+        Error::Io(error)
     }
 }
 
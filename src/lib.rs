@@ -3,6 +3,7 @@ pub mod config;
 pub(crate) mod error;
 pub mod models;
 pub mod proton_manager;
+pub mod sources;
 pub(crate) mod utilities;
 
 #[macro_use]
@@ -1,13 +1,19 @@
 #[macro_use]
 extern crate log;
 
+use std::io::{IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+
+use chrono::{TimeZone, Utc};
 use clap::Parser;
+use indicatif::{ProgressBar, ProgressStyle};
 use prettytable::{row, Row, Table};
-use pup_rs::proton_manager::ProtonManager;
+use pup_rs::proton_manager::{ProgressEvent, ProgressPhase, ProgressSink, ProtonManager};
 
 use pup_rs::cli;
 use pup_rs::cli::{Cli, Command};
 use pup_rs::config::Config;
+use pup_rs::models::asset::Asset;
 use pup_rs::models::release::Release;
 
 #[tokio::main]
@@ -49,7 +55,13 @@ fn get_proton_manager(config: Config, maybe_module_name: Option<String>) -> Prot
         module_name,
         config.path.to_str().unwrap()
     );
-    ProtonManager::new(module_name, &module_config)
+    match ProtonManager::new(module_name.clone(), &module_config) {
+        Ok(pm) => pm,
+        Err(e) => {
+            error!("Module {} is misconfigured: {}", module_name, e);
+            std::process::exit(1);
+        }
+    }
 }
 
 pub async fn handle_command(cli: Cli, config: Config) {
@@ -59,6 +71,7 @@ pub async fn handle_command(cli: Cli, config: Config) {
         Some(command) => match command {
             Command::List(list) => handle_list(pm, list).await,
             Command::Install(install) => handle_install(pm, install).await,
+            Command::Remove(remove) => handle_remove(pm, remove).await,
         },
         None => check_for_updates(pm).await,
     }
@@ -74,16 +87,22 @@ async fn check_for_updates(mut pm: ProtonManager) {
     let latest_release = releases.first().unwrap();
     let installed_releases = pm.get_releases(1, true).await.unwrap();
     if installed_releases.is_empty() {
-        info!(
-            "The latest release {} from {} is not installed.",
-            latest_release.tag_name,
-            latest_release.created_at.unwrap().format("%Y-%m-%d")
-        );
+        match latest_release.created_at {
+            Some(created_at) => info!(
+                "The latest release {} from {} is not installed.",
+                latest_release.tag_name,
+                created_at.format("%Y-%m-%d")
+            ),
+            None => info!(
+                "The latest release {} is not installed.",
+                latest_release.tag_name
+            ),
+        }
         return;
     }
 
     let installed_release = installed_releases.first().unwrap();
-    if latest_release.tag_name != installed_release.tag_name {
+    if latest_release > installed_release {
         info!(
             "A new release is available: {} (installed: {})",
             latest_release.tag_name, installed_release.tag_name
@@ -97,10 +116,35 @@ async fn check_for_updates(mut pm: ProtonManager) {
 }
 
 async fn handle_list(mut pm: ProtonManager, list: cli::List) {
-    let releases = pm
-        .get_releases(list.count.unwrap_or(10), list.installed)
-        .await
-        .unwrap();
+    if list.installed {
+        return handle_list_installed(pm, list.fetch).await;
+    }
+
+    let count = list.count.unwrap_or(10);
+    let per_page = if list.all {
+        100
+    } else {
+        count.clamp(1, 100) as u8
+    };
+    let since = list
+        .since
+        .map(|date| Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()));
+
+    let mut releases = Vec::new();
+    let mut stream = pm.stream_releases(per_page, list.page.unwrap_or(1));
+    while let Some(release) = stream.next().await.unwrap() {
+        if since
+            .zip(release.published_at)
+            .is_some_and(|(since, published_at)| published_at < since)
+        {
+            break;
+        }
+
+        releases.push(release);
+        if !list.all && releases.len() >= count {
+            break;
+        }
+    }
 
     if releases.is_empty() {
         info!("No releases found.");
@@ -108,14 +152,8 @@ async fn handle_list(mut pm: ProtonManager, list: cli::List) {
     }
 
     info!(
-        "The following releases are {} @{}/{}:",
-        if list.installed {
-            "installed from"
-        } else {
-            "available at"
-        },
-        pm.config.owner,
-        pm.config.repo
+        "The following releases are available at {}:",
+        pm.source_location()
     );
 
     let mut table = Table::new();
@@ -127,8 +165,39 @@ async fn handle_list(mut pm: ProtonManager, list: cli::List) {
     table.printstd();
 }
 
+async fn handle_list_installed(mut pm: ProtonManager, fetch: bool) {
+    let tools = pm.scan_install_dir(fetch).await.unwrap();
+
+    if tools.is_empty() {
+        info!("No releases found.");
+        return;
+    }
+
+    info!(
+        "The following tools are installed in {}:",
+        pm.config.install_dir.display()
+    );
+
+    let mut table = Table::new();
+    table.set_format(*prettytable::format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(row!["Tag", "Date", "Status", "Path"]);
+    for tool in tools {
+        let date = tool
+            .release
+            .as_ref()
+            .and_then(|r| r.published_at)
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        table.add_row(row![tool.name, date, tool.state, tool.path.display()]);
+    }
+    table.printstd();
+}
+
 fn get_list_table_row(release: &Release) -> Row {
-    let date = release.published_at.unwrap().format("%Y-%m-%d");
+    let date = release
+        .published_at
+        .map(|d| d.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
     row![
         release.tag_name,
         date,
@@ -142,5 +211,81 @@ fn get_list_table_row(release: &Release) -> Row {
 }
 
 async fn handle_install(mut pm: ProtonManager, install: cli::Install) {
+    if std::io::stdin().is_terminal() {
+        pm.set_asset_chooser(Box::new(choose_asset));
+    }
+    if std::io::stderr().is_terminal() {
+        pm.set_progress_sink(progress_bar_sink());
+    }
+
     pm.install_release(&install.tag).await.unwrap();
 }
+
+/// Draws a progress bar for each phase of an install.
+fn progress_bar_sink() -> ProgressSink {
+    let current: Mutex<Option<(ProgressPhase, ProgressBar)>> = Mutex::new(None);
+
+    Arc::new(move |event: &ProgressEvent| {
+        let mut current = current.lock().unwrap();
+        if current.as_ref().map(|(phase, _)| *phase) != Some(event.phase) {
+            if let Some((_, bar)) = current.take() {
+                bar.finish_and_clear();
+            }
+
+            let template = match event.total {
+                Some(_) => {
+                    "{msg:>11} [{bar:40}] {bytes}/{total_bytes} {binary_bytes_per_sec} ETA {eta}"
+                }
+                None => "{msg:>11} {spinner} {bytes} {binary_bytes_per_sec}",
+            };
+            let bar = ProgressBar::new(event.total.unwrap_or(0));
+            bar.set_style(
+                ProgressStyle::with_template(template)
+                    .unwrap()
+                    .progress_chars("=> "),
+            );
+            bar.set_message(match event.phase {
+                ProgressPhase::Download => "Downloading",
+                ProgressPhase::Verify => "Verifying",
+                ProgressPhase::Extract => "Extracting",
+            });
+            *current = Some((event.phase, bar));
+        }
+
+        let (_, bar) = current.as_ref().unwrap();
+        bar.set_position(event.bytes);
+        if event.done {
+            bar.finish_and_clear();
+            *current = None;
+        }
+    })
+}
+
+/// Asks which asset to install when several match.
+fn choose_asset(release: &Release, assets: &[Asset]) -> Option<usize> {
+    println!("Release {} has several matching assets:", release.tag_name);
+    for (index, asset) in assets.iter().enumerate() {
+        println!("  {}) {}", index + 1, asset.name);
+    }
+    print!("Choose an asset [1-{}]: ", assets.len());
+    std::io::stdout().flush().ok()?;
+
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer).ok()?;
+    answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|choice| (1..=assets.len()).contains(choice))
+        .map(|choice| choice - 1)
+}
+
+async fn handle_remove(mut pm: ProtonManager, remove: cli::Remove) {
+    if remove.all_but_latest {
+        pm.remove_all_but_latest(remove.keep_cache).await.unwrap();
+    } else {
+        pm.remove_release(&remove.tag.unwrap(), remove.keep_cache)
+            .await
+            .unwrap();
+    }
+}
@@ -8,6 +8,9 @@ pub struct Asset {
     pub name: String,
     pub browser_download_url: Url,
     pub updated_at: DateTime<Utc>,
+    /// The API endpoint for the asset, which serves private assets to authenticated clients.
+    #[serde(default)]
+    pub api_url: Option<Url>,
 }
 
 impl From<octocrab::models::repos::Asset> for Asset {
@@ -16,6 +19,7 @@ impl From<octocrab::models::repos::Asset> for Asset {
             name: asset.name,
             browser_download_url: asset.browser_download_url,
             updated_at: asset.updated_at,
+            api_url: Some(asset.url),
         }
     }
 }
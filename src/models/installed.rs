@@ -0,0 +1,30 @@
+use crate::models::release::Release;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallState {
+    /// Installed by pup and recorded in the releases cache.
+    Managed,
+    /// Matches an upstream release, but was installed by something other than pup.
+    Unmanaged,
+    /// Could not be matched to an upstream release.
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct InstalledTool {
+    pub name: String,
+    pub path: PathBuf,
+    pub state: InstallState,
+    pub release: Option<Release>,
+}
+
+impl std::fmt::Display for InstallState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallState::Managed => write!(f, "managed"),
+            InstallState::Unmanaged => write!(f, "unmanaged"),
+            InstallState::Unknown => write!(f, "unknown"),
+        }
+    }
+}
@@ -1,2 +1,4 @@
 pub mod asset;
+pub mod installed;
 pub mod release;
+pub mod version;
@@ -1,4 +1,5 @@
 use crate::models::asset::Asset;
+use crate::models::version::Version;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
@@ -11,7 +12,16 @@ pub struct Release {
     pub created_at: Option<DateTime<Utc>>,
     pub published_at: Option<DateTime<Utc>>,
     pub assets: Vec<Asset>,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub draft: bool,
+    /// The release notes.
+    #[serde(default)]
+    pub body: Option<String>,
     pub installed_in: Option<PathBuf>,
+    #[serde(default)]
+    pub installed_contents: Vec<String>,
 }
 
 impl From<octocrab::models::repos::Release> for Release {
@@ -22,7 +32,11 @@ impl From<octocrab::models::repos::Release> for Release {
             published_at: release.published_at,
             created_at: release.created_at,
             assets: release.assets.into_iter().map(Asset::from).collect(),
+            prerelease: release.prerelease,
+            draft: release.draft,
+            body: release.body,
             installed_in: None,
+            installed_contents: Vec::new(),
         }
     }
 }
@@ -33,9 +47,20 @@ impl PartialEq for Release {
     }
 }
 
+impl Release {
+    pub fn version(&self) -> Version {
+        Version::parse(&self.tag_name)
+    }
+}
+
+/// Releases are ordered by the version in their tag, then by publish date.
 impl PartialOrd for Release {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.published_at.partial_cmp(&other.published_at)
+        Some(
+            self.version()
+                .cmp(&other.version())
+                .then_with(|| self.published_at.cmp(&other.published_at)),
+        )
     }
 }
 
@@ -0,0 +1,156 @@
+use std::cmp::Ordering;
+
+use crate::utilities::pattern;
+
+const PRERELEASE_MARKERS: [&str; 4] = ["alpha", "beta", "pre", "rc"];
+
+/// A version parsed from a release tag, understanding the GE naming schemes
+/// (`GE-Proton9-20`, `lutris-GE-Proton8-26`, `7.0rc6-GE-1`). Versions compare by their numbers,
+/// so `GE-Proton9-20` > `GE-Proton9-3` > `7.0rc6-GE-1` > `6.21-GE-2`, and a prerelease such as
+/// `rc6` sorts before the same numbers without one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    /// The text before the first number, e.g. `GE-Proton`.
+    pub prefix: String,
+    pub numbers: Vec<u64>,
+    /// A prerelease marker and its number, e.g. `("rc", 6)`.
+    pub prerelease: Option<(String, u64)>,
+}
+
+impl Version {
+    pub fn parse(tag: &str) -> Self {
+        let mut prefix = String::new();
+        let mut numbers = Vec::new();
+        let mut prerelease = None;
+        let mut text = String::new();
+        let mut chars = tag.chars().peekable();
+
+        while let Some(&c) = chars.peek() {
+            if !c.is_ascii_digit() {
+                text.push(c);
+                chars.next();
+                continue;
+            }
+
+            let mut digits = String::new();
+            while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                digits.push(d);
+                chars.next();
+            }
+            let number = digits.parse().unwrap_or(u64::MAX);
+
+            let marker = text.to_ascii_lowercase();
+            let marker = marker.trim_matches(|c: char| !c.is_ascii_alphabetic());
+            if numbers.is_empty() && prefix.is_empty() {
+                prefix = text.clone();
+            }
+
+            if PRERELEASE_MARKERS.contains(&marker) && !numbers.is_empty() {
+                prerelease = Some((marker.to_string(), number));
+            } else {
+                numbers.push(number);
+            }
+            text.clear();
+        }
+
+        if numbers.is_empty() {
+            prefix = text;
+        }
+
+        Self {
+            prefix,
+            numbers,
+            prerelease,
+        }
+    }
+}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.numbers
+            .cmp(&other.numbers)
+            .then_with(|| match (&self.prerelease, &other.prerelease) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(b),
+            })
+            .then_with(|| self.prefix.cmp(&other.prefix))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Which release to install, as given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionConstraint {
+    /// The newest release.
+    Latest,
+    /// The release before the newest one.
+    Previous,
+    /// A release compared to a version with the same prefix, e.g. `>=GE-Proton8-25`.
+    Compare(Ordering, bool, Version),
+    /// A tag pattern with `*` and `?` wildcards, e.g. `GE-Proton9-*`.
+    Pattern(String),
+    /// An exact tag, or a tag prefix such as `GE-Proton9` which matches `GE-Proton9-20`.
+    Tag(String),
+}
+
+impl VersionConstraint {
+    pub fn parse(constraint: &str) -> Self {
+        let constraint = constraint.trim();
+        match constraint {
+            "latest" => return VersionConstraint::Latest,
+            "previous" => return VersionConstraint::Previous,
+            _ => {}
+        }
+
+        let operators = [
+            (">=", Ordering::Greater, true),
+            ("<=", Ordering::Less, true),
+            (">", Ordering::Greater, false),
+            ("<", Ordering::Less, false),
+            ("=", Ordering::Equal, true),
+        ];
+        for (operator, ordering, or_equal) in operators {
+            if let Some(version) = constraint.strip_prefix(operator) {
+                return VersionConstraint::Compare(
+                    ordering,
+                    or_equal,
+                    Version::parse(version.trim()),
+                );
+            }
+        }
+
+        if constraint.contains(['*', '?']) {
+            return VersionConstraint::Pattern(constraint.to_string());
+        }
+
+        VersionConstraint::Tag(constraint.to_string())
+    }
+
+    /// Whether a tag satisfies the constraint. `Latest` and `Previous` depend on the other
+    /// releases, so they match every tag and are resolved by position instead.
+    pub fn matches(&self, tag: &str) -> bool {
+        match self {
+            VersionConstraint::Latest | VersionConstraint::Previous => true,
+            VersionConstraint::Compare(ordering, or_equal, version) => {
+                let actual = Version::parse(tag);
+                if actual.prefix != version.prefix {
+                    return false;
+                }
+
+                let actual = actual.cmp(version);
+                actual == *ordering || (*or_equal && actual == Ordering::Equal)
+            }
+            VersionConstraint::Pattern(pattern) => pattern::glob_match(pattern, tag),
+            VersionConstraint::Tag(prefix) => tag
+                .strip_prefix(prefix.as_str())
+                .is_some_and(|rest| !rest.starts_with(|c: char| c.is_ascii_digit())),
+        }
+    }
+}
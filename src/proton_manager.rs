@@ -1,38 +1,101 @@
-use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use base_url::BaseUrl;
 use checksums::Algorithm;
 use dirs::cache_dir;
 
-
 use crate::config::ConfigModule;
 use crate::error::Error;
 use crate::models::asset::Asset;
+use crate::models::installed::{InstallState, InstalledTool};
 use crate::models::release::Release;
+use crate::models::version::VersionConstraint;
+use crate::sources;
+use crate::sources::{PageCursor, ReleaseSource};
 use crate::utilities::cache::Cache;
+use crate::utilities::checksum;
+use crate::utilities::downloader;
 use crate::utilities::downloader::Downloader;
 use crate::utilities::downloader::FileGetter;
+pub use crate::utilities::downloader::{channel_sink, ProgressEvent, ProgressPhase, ProgressSink};
 use crate::utilities::extract;
+use crate::utilities::http::HttpClient;
+use crate::utilities::ledger::{AssetKey, Ledger};
+use crate::utilities::pattern::NamePattern;
+use crate::utilities::scanner;
+use crate::utilities::scanner::ToolDirectory;
+use crate::utilities::signature;
+use crate::utilities::signature::{Signature, TrustedKeys};
+use crate::utilities::staging::StagedInstall;
+
+/// How many of the most recent releases install constraints are resolved against before
+/// searching older pages.
+const RESOLVE_WINDOW: usize = 100;
+
+/// How many releases install constraints are resolved against at most, when none of the most
+/// recent ones match.
+const RESOLVE_LIMIT: usize = 1000;
+
+/// Archive types to fall back on when several assets match, most preferred first.
+const DEFAULT_ASSET_PREFERENCE: [&str; 5] =
+    ["*.tar.gz", "*.tar.xz", "*.tar.zst", "*.tar.bz2", "*.zip"];
+
+/// Picks one of several assets matching a release, returning its index, or `None` to give up.
+pub type AssetChooser = Box<dyn Fn(&Release, &[Asset]) -> Option<usize> + Send + Sync>;
 
 pub struct ProtonManager {
     pub config: ConfigModule,
     releases_cache: Cache<Release>,
+    source: Box<dyn ReleaseSource>,
+    http: HttpClient,
+    asset_chooser: Option<AssetChooser>,
+    ledger: Arc<Mutex<Ledger>>,
+    progress: Option<ProgressSink>,
 }
 
 impl ProtonManager {
-    pub fn new(name: String, config: &ConfigModule) -> Self {
-        let releases_cache_file = cache_dir()
-            .unwrap()
+    /// Sets up the manager for a config module, failing if the module's settings, such as its
+    /// source or network settings, are invalid.
+    pub fn new(name: String, config: &ConfigModule) -> Result<Self, Error> {
+        let module_cache_dir = cache_dir()
+            .ok_or(Error::NotFound("cache dir".to_string()))?
             .join("pup-rs")
-            .join(name)
-            .join("releases.json");
-        let releases_cache = Cache::<Release>::new(releases_cache_file, 100);
+            .join(name);
+        // Installed releases are kept however old they are, so they can still be removed.
+        let releases_cache = Cache::<Release>::new(module_cache_dir.join("releases.json"), 100)
+            .keep_if(|release| release.installed_in.is_some());
+        let http = HttpClient::new(&config.network)?;
 
-        Self {
+        Ok(Self {
             config: config.clone(),
             releases_cache,
-        }
+            source: sources::from_config(config, &module_cache_dir, &http)?,
+            http,
+            asset_chooser: None,
+            ledger: Arc::new(Mutex::new(Ledger::new(
+                module_cache_dir.join("ledger.json"),
+                config.pinning,
+            ))),
+            progress: None,
+        })
+    }
+
+    /// Sends progress events for the download, verification and extraction of installs to
+    /// `sink`. [`channel_sink`] turns a channel into a sink.
+    pub fn set_progress_sink(&mut self, sink: ProgressSink) {
+        self.progress = Some(sink);
+    }
+
+    /// Sets how to choose between several assets matching a release. Without a chooser,
+    /// installing such a release fails with a list of the candidates.
+    pub fn set_asset_chooser(&mut self, chooser: AssetChooser) {
+        self.asset_chooser = Some(chooser);
+    }
+
+    /// Describes where this manager fetches releases from.
+    pub fn source_location(&self) -> String {
+        self.source.location()
     }
 
     pub async fn get_releases(
@@ -40,76 +103,274 @@ impl ProtonManager {
         count: u8,
         installed: bool,
     ) -> Result<Vec<Release>, Error> {
-        let releases = match installed {
-            true => self.get_installed_releases().await?,
-            false => self.fetch_releases(count).await?,
-        };
+        match installed {
+            true => self.get_installed_releases().await,
+            false => self.fetch_releases(count).await,
+        }
+    }
 
-        self.releases_cache.extend(releases.clone());
-        Ok(releases)
+    /// Streams releases in the module's channel newest first, starting at page `first_page` of `per_page` releases and
+    /// following the source's pagination from there.
+    pub fn stream_releases(&mut self, per_page: u8, first_page: u32) -> ReleaseStream<'_> {
+        ReleaseStream {
+            manager: self,
+            per_page,
+            cursor: Some(PageCursor::Number(first_page)),
+            buffer: Vec::new().into_iter(),
+        }
     }
 
-    pub async fn get_installed_releases(&self) -> Result<Vec<Release>, Error> {
-        let releases = self
+    /// Copies what the cache knows about where a release is installed.
+    fn with_install_state(&self, mut release: Release) -> Release {
+        if let Some(cached) = self
             .releases_cache
             .data
             .iter()
-            .filter(|r| r.installed_in.is_some())
-            .cloned()
+            .find(|r| r.tag_name == release.tag_name && r.installed_in.is_some())
+        {
+            release.installed_in = cached.installed_in.clone();
+            release.installed_contents = cached.installed_contents.clone();
+        }
+
+        release
+    }
+
+    /// Returns the releases found in the install directory, whether or not pup installed them.
+    /// Only releases in the cache are recognized.
+    pub async fn get_installed_releases(&mut self) -> Result<Vec<Release>, Error> {
+        let mut releases: Vec<Release> = self
+            .scan_install_dir(false)
+            .await?
+            .into_iter()
+            .filter_map(|tool| tool.release)
             .collect();
 
+        releases.sort_by(|a, b| b.partial_cmp(a).unwrap());
         Ok(releases)
     }
 
+    /// Scans the install directory and matches each tool directory to a cached release. With
+    /// `fetch`, releases are fetched first if a directory matches none of them.
+    pub async fn scan_install_dir(&mut self, fetch: bool) -> Result<Vec<InstalledTool>, Error> {
+        let directories = scanner::scan(&self.config.install_dir)?;
+
+        let mut known_releases = self.releases_cache.get();
+        let has_unmatched = directories.iter().any(|d| {
+            self.find_managed_release(&known_releases, d).is_none()
+                && scanner::match_tag(d, known_releases.iter().map(|r| r.tag_name.as_str()))
+                    .is_none()
+        });
+
+        if fetch && has_unmatched {
+            debug!("Fetching releases to match unknown tool directories.");
+            match self.fetch_all_releases(100).await {
+                Ok(releases) => known_releases = releases,
+                Err(e) => warn!("Could not fetch releases: {:?}", e),
+            }
+        }
+
+        let mut tools = Vec::new();
+        let mut managed_tags = Vec::new();
+        for directory in directories {
+            let tool = match self.find_managed_release(&known_releases, &directory) {
+                Some(release) => {
+                    managed_tags.push(release.tag_name.clone());
+                    InstalledTool {
+                        name: release.tag_name.clone(),
+                        path: directory.path,
+                        state: InstallState::Managed,
+                        release: Some(release.clone()),
+                    }
+                }
+                None => {
+                    let tag = scanner::match_tag(
+                        &directory,
+                        known_releases.iter().map(|r| r.tag_name.as_str()),
+                    );
+                    match known_releases
+                        .iter()
+                        .find(|r| Some(r.tag_name.as_str()) == tag)
+                    {
+                        Some(release) => {
+                            let mut release = release.clone();
+                            release.installed_in = Some(self.config.install_dir.clone());
+                            release.installed_contents = vec![directory.dir_name];
+                            InstalledTool {
+                                name: release.tag_name.clone(),
+                                path: directory.path,
+                                state: InstallState::Unmanaged,
+                                release: Some(release),
+                            }
+                        }
+                        None => InstalledTool {
+                            name: directory.names[0].clone(),
+                            path: directory.path,
+                            state: InstallState::Unknown,
+                            release: None,
+                        },
+                    }
+                }
+            };
+
+            tools.push(tool);
+        }
+
+        // Forget releases that were removed from disk without going through pup.
+        for mut release in known_releases {
+            if release.installed_in.as_ref() == Some(&self.config.install_dir)
+                && !managed_tags.contains(&release.tag_name)
+            {
+                debug!("Release {} is no longer on disk.", release.tag_name);
+                release.installed_in = None;
+                release.installed_contents.clear();
+                self.releases_cache.update(release)?;
+            }
+        }
+
+        Ok(tools)
+    }
+
+    fn find_managed_release<'a>(
+        &self,
+        releases: &'a [Release],
+        directory: &ToolDirectory,
+    ) -> Option<&'a Release> {
+        releases.iter().find(|r| {
+            r.installed_in.as_ref() == Some(&self.config.install_dir)
+                && (r.installed_contents.contains(&directory.dir_name)
+                    || (r.installed_contents.is_empty() && r.tag_name == directory.dir_name))
+        })
+    }
+
+    /// Fetches the latest releases and returns the known releases in the module's channel.
     pub async fn fetch_releases(&mut self, count: u8) -> Result<Vec<Release>, Error> {
-        let releases: Vec<Release> = octocrab::instance()
-            .repos(self.config.owner.as_str(), self.config.repo.as_str())
-            .releases()
-            .list()
-            .per_page(count)
-            .send()
-            .await
-            .map(|r| r.items)
-            .map_err(|e| Error::Api(e.to_string()))?
+        let channel = self.config.channel;
+        Ok(self
+            .fetch_all_releases(count)
+            .await?
             .into_iter()
-            .map(Release::from)
-            .collect();
+            .filter(|r| channel.includes(r))
+            .collect())
+    }
 
-        self.releases_cache.extend(releases);
+    /// Like [`Self::fetch_releases`], but regardless of channel, so installed prereleases can
+    /// still be recognized.
+    async fn fetch_all_releases(&mut self, count: u8) -> Result<Vec<Release>, Error> {
+        let releases = self.source.list_releases(count).await?;
+        self.remember_releases(releases);
         Ok(self.releases_cache.get())
     }
 
+    /// Adds fetched releases to the cache, keeping what it knows about their install state and
+    /// warning about assets re-uploaded since they were pinned.
+    fn remember_releases(&mut self, releases: Vec<Release>) -> Vec<Release> {
+        let location = self.source.location();
+        let releases = releases
+            .into_iter()
+            .map(|r| {
+                // Listing still works under `pinning = "refuse"`, only installing is refused.
+                if let Err(e) = self.ledger.lock().unwrap().check_release(&location, &r) {
+                    warn!("{:?}", e);
+                }
+                self.with_install_state(r)
+            })
+            .collect::<Vec<_>>();
+
+        self.releases_cache.extend(releases.clone());
+        releases
+    }
+
     pub async fn get_release(&self, tag: &str) -> Result<Release, Error> {
-        let release = octocrab::instance()
-            .repos(self.config.owner.as_str(), self.config.repo.as_str())
-            .releases()
-            .get_by_tag(tag)
-            .await
-            .map_err(|e| Error::Api(e.to_string()))?;
+        let release = self.source.get_release(tag).await?;
         debug!(
-            "Found release {} ({}) from {}",
+            "Found release {} from {}",
             release.tag_name,
-            release.id,
-            release.created_at.unwrap().format("%Y-%m-%d")
+            release
+                .published_at
+                .map(|d| d.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
         );
 
-        Ok(release.into())
+        Ok(release)
     }
 
-    pub async fn install_release(&mut self, tag: &str) -> Result<(), Error> {
+    /// Finds the release to install for a constraint: `latest`, `previous`, an exact tag, a tag
+    /// prefix such as `GE-Proton9`, a pattern such as `GE-Proton9-*` or a comparison such as
+    /// `>=GE-Proton8-25`. The newest matching release by version in the module's channel wins; an
+    /// exact tag is installed whatever its channel.
+    pub async fn resolve_release(&mut self, query: &str) -> Result<Release, Error> {
+        let constraint = VersionConstraint::parse(query);
+        if let VersionConstraint::Tag(tag) = &constraint {
+            match self.get_release(tag).await {
+                Err(Error::NotFound(_)) => {
+                    debug!("No release tagged {}, trying it as a prefix.", tag)
+                }
+                result => return result,
+            }
+        }
+
+        let mut matches = Vec::new();
+        let mut seen = 0;
+        let mut stream = self.stream_releases(RESOLVE_WINDOW as u8, 1);
+        while let Some(release) = stream.next().await? {
+            seen += 1;
+            if constraint.matches(&release.tag_name) {
+                matches.push(release);
+            }
+
+            if (seen >= RESOLVE_WINDOW && !matches.is_empty()) || seen >= RESOLVE_LIMIT {
+                break;
+            }
+        }
+
+        matches.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        let index = match constraint {
+            VersionConstraint::Previous => 1,
+            _ => 0,
+        };
+
+        let release = matches
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| Error::NotFound(format!("release matching {}", query)))?;
+        debug!("Resolved {} to {}", query, release.tag_name);
+        Ok(release)
+    }
+
+    pub async fn install_release(&mut self, constraint: &str) -> Result<(), Error> {
+        let mut release = self.resolve_release(constraint).await?;
+        self.ledger
+            .lock()
+            .unwrap()
+            .check_release(&self.source.location(), &release)?;
+        let tag = release.tag_name.clone();
         info!("Installing release {}", tag);
-        let mut release = self.get_release(tag).await?;
         let downloaded_file = self.download_release(&release).await?;
 
+        // The install directory is only touched once the new install is complete, so a failure
+        // leaves the previous install in place.
+        let mut staged = StagedInstall::new(&self.config.install_dir)?;
         info!(
             "Extracting {} to {}",
             downloaded_file.display(),
-            self.config.install_dir.display()
+            staged.path().display()
         );
-        let contents = extract::extract(&downloaded_file, &self.config.install_dir)?;
+        // Extraction is blocking work that can take minutes, so it runs off the async runtime.
+        let (archive, destination) = (downloaded_file.clone(), staged.path());
+        let options = extract::ExtractOptions::from_config(&self.config);
+        let progress = self.progress.clone();
+        let contents = tokio::task::spawn_blocking(move || {
+            extract::extract(&archive, &destination, &options, progress)
+        })
+        .await
+        .map_err(|e| Error::new(&format!("Extraction stopped: {}", e)))??;
         info!("Extracted files: {:?}", contents);
+        staged.validate(&contents, self.config.required_files.as_deref())?;
+        staged.commit(&contents)?;
+        drop(staged);
 
         release.installed_in = Some(self.config.install_dir.clone());
+        release.installed_contents = contents.clone();
         self.releases_cache.update(release).unwrap();
 
         if self.config.symlink.is_some() && cfg!(unix) && contents.len() == 1 {
@@ -124,6 +385,151 @@ impl ProtonManager {
         Ok(())
     }
 
+    pub async fn remove_release(&mut self, tag: &str, keep_cache: bool) -> Result<(), Error> {
+        info!("Removing release {}", tag);
+        let mut release = self
+            .get_installed_releases()
+            .await?
+            .into_iter()
+            .find(|r| r.tag_name == tag)
+            .ok_or_else(|| Error::NotFound(format!("installed release {}", tag)))?;
+
+        let installed_paths = get_installed_paths(&release);
+        let symlink_removed = self
+            .get_symlink_target()
+            .is_some_and(|target| installed_paths.contains(&target));
+
+        let mut removed_any = false;
+        for path in &installed_paths {
+            if path.is_symlink() || path.is_file() {
+                std::fs::remove_file(path)?;
+            } else if path.is_dir() {
+                std::fs::remove_dir_all(path)?;
+            } else {
+                continue;
+            }
+
+            info!("Removed {}", path.display());
+            removed_any = true;
+        }
+
+        if !removed_any {
+            warn!(
+                "No files found for release {}, marking it as not installed.",
+                tag
+            );
+        }
+
+        release.installed_in = None;
+        release.installed_contents.clear();
+        self.releases_cache.update(release.clone())?;
+
+        if !keep_cache {
+            self.remove_cached_download(&release).await?;
+        }
+
+        if symlink_removed {
+            self.repoint_symlink().await?;
+        }
+
+        info!("Release {} removed successfully.", tag);
+        Ok(())
+    }
+
+    /// Removes every release pup installed except the newest. Tools installed by something
+    /// else are left alone.
+    pub async fn remove_all_but_latest(&mut self, keep_cache: bool) -> Result<(), Error> {
+        let mut installed_releases: Vec<Release> = self
+            .scan_install_dir(false)
+            .await?
+            .into_iter()
+            .filter(|tool| tool.state == InstallState::Managed)
+            .filter_map(|tool| tool.release)
+            .collect();
+        installed_releases.sort_by(|a, b| b.partial_cmp(a).unwrap());
+
+        if installed_releases.len() < 2 {
+            info!("No releases to remove.");
+            return Ok(());
+        }
+
+        for release in installed_releases.iter().skip(1) {
+            self.remove_release(&release.tag_name, keep_cache).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn remove_cached_download(&self, release: &Release) -> Result<(), Error> {
+        let assets = match self.find_assets(release) {
+            Ok(assets) => assets,
+            Err(_) => return Ok(()),
+        };
+
+        for asset in assets {
+            let partial_name = format!("{}.part", asset.name);
+            for name in [&asset.name, &partial_name] {
+                let cached_file = self.config.cache_dir.join(name);
+                if cached_file.is_file() {
+                    debug!("Removing cached file {}", cached_file.display());
+                    tokio::fs::remove_file(&cached_file).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_symlink_path(&self) -> Option<PathBuf> {
+        if cfg!(not(unix)) {
+            return None;
+        }
+
+        self.config
+            .symlink
+            .as_ref()
+            .map(|symlink| self.config.install_dir.join(symlink))
+    }
+
+    fn get_symlink_target(&self) -> Option<PathBuf> {
+        let symlink_path = self.get_symlink_path()?;
+        if !symlink_path.is_symlink() {
+            return None;
+        }
+
+        let target = std::fs::read_link(&symlink_path).ok()?;
+        Some(self.config.install_dir.join(target))
+    }
+
+    /// Points the symlink at the latest installed release, or removes it if none is left.
+    async fn repoint_symlink(&mut self) -> Result<(), Error> {
+        let symlink_path = match self.get_symlink_path() {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let latest_dir = self
+            .get_installed_releases()
+            .await?
+            .iter()
+            .map(get_installed_paths)
+            .find(|paths| paths.len() == 1 && paths[0].is_dir())
+            .map(|paths| paths[0].clone());
+
+        match latest_dir {
+            Some(dir) => {
+                self.update_symlink(&dir, self.config.symlink.clone().unwrap())
+                    .await
+            }
+            None => {
+                info!("Removing symlink {}", symlink_path.display());
+                std::fs::remove_file(&symlink_path)?;
+            }
+        }
+
+        Ok(())
+    }
+
     async fn update_symlink(&self, path: &PathBuf, symlink: String) {
         if cfg!(not(unix)) {
             return;
@@ -147,112 +553,281 @@ impl ProtonManager {
     }
 
     async fn download_release(&self, release: &Release) -> Result<PathBuf, Error> {
-        let asset = self.get_asset(release).await?;
+        let asset = self.get_asset(release)?;
         let download_url = BaseUrl::try_from(asset.browser_download_url.as_str())?;
-        let filename = download_url.path_segments().last().unwrap().to_string();
+        let filename = download_url
+            .path_segments()
+            .next_back()
+            .unwrap()
+            .to_string();
         debug!("Found asset {} at {}", filename, download_url);
 
+        let (request_url, headers) = self.source.asset_request(&asset);
+        let request_url = BaseUrl::try_from(request_url.as_str())?;
         let (checksum, checksum_algorithm) = self.fetch_checksum(release, &filename).await?;
         let download_path = self.config.cache_dir.join(&filename);
         let cache_dir = self.config.cache_dir.to_str();
 
-        let downloader = Downloader::new(
-            Option::from(download_url),
+        let mut downloader = Downloader::new(
+            Option::from(request_url),
             Option::from(download_path.to_path_buf()),
             cache_dir,
             Option::from(checksum.as_str()),
             Option::from(checksum_algorithm),
             true,
+        )
+        .with_client(self.http.clone())
+        .with_headers(headers)
+        .with_progress(self.progress.clone())
+        .with_ledger(
+            self.ledger.clone(),
+            AssetKey {
+                repository: self.source.location(),
+                tag: release.tag_name.clone(),
+                asset: asset.name.clone(),
+                updated_at: asset.updated_at,
+            },
         );
+
+        let keys = TrustedKeys::from_config(&self.config);
+        if !keys.is_empty() {
+            let signature = self.fetch_signature(release, &filename, &keys).await?;
+            downloader = downloader.with_signature(signature, keys);
+        }
+
         let file = downloader.get_file().await?;
 
         Ok(file.into())
     }
 
+    /// Downloads the detached signature for `filename` of a kind there is a trusted key for.
+    /// Returns `None` if the release has no such signature for the file.
+    async fn fetch_signature(
+        &self,
+        release: &Release,
+        filename: &str,
+        keys: &TrustedKeys,
+    ) -> Result<Option<Signature>, Error> {
+        let (asset, kind) = match release.assets.iter().find_map(|a| {
+            signature::signature_kind(&a.name, filename)
+                .filter(|kind| keys.supports(*kind))
+                .map(|kind| (a, kind))
+        }) {
+            Some(signature) => signature,
+            None => return Ok(None),
+        };
+
+        debug!("Found signature {}", asset.name);
+        let (url, headers) = self.source.asset_request(asset);
+        Ok(Some(Signature {
+            kind,
+            name: asset.name.clone(),
+            data: downloader::get_bytes(&self.http, &url, headers).await?,
+        }))
+    }
+
+    /// Finds the checksum for `filename`, looking in its own checksum file first, then in any
+    /// other checksum file in the release, such as `SHA256SUMS`, and finally in the release notes.
     async fn fetch_checksum(
         &self,
         release: &Release,
         filename: &str,
     ) -> Result<(String, Algorithm), Error> {
-        let basename = filename.split('.').next().unwrap();
-        debug!("Fetching checksum for {}", basename);
-
-        let hash_types = HashMap::from([
-            ("sha512sum", Algorithm::SHA2512),
-            ("sha256sum", Algorithm::SHA2256),
-            ("sha1sum", Algorithm::SHA1),
-            ("md5sum", Algorithm::MD5),
-        ]);
+        debug!("Fetching checksum for {}", filename);
+        let stem = extract::archive_stem(filename).unwrap_or(filename);
 
-        let asset_filenames = release
+        let (siblings, others): (Vec<&Asset>, Vec<&Asset>) = release
             .assets
             .iter()
-            .map(|a| {
-                a.browser_download_url
-                    .path_segments()
-                    .unwrap()
-                    .last()
-                    .unwrap()
-            })
-            .collect::<Vec<_>>();
-        debug!("Assets: {:?}", asset_filenames);
+            .filter(|a| checksum::is_checksum_file(&a.name))
+            .partition(|a| checksum::is_sibling_checksum(&a.name, filename, stem));
 
-        let assets_with_same_basename = asset_filenames
-            .iter()
-            .filter(|a| a.starts_with(basename))
-            .collect::<Vec<_>>();
+        let mut searched = Vec::new();
+        let candidates = siblings
+            .into_iter()
+            .map(|a| (a, true))
+            .chain(others.into_iter().map(|a| (a, false)));
+        for (asset, sibling) in candidates {
+            let (url, headers) = self.source.asset_request(asset);
+            let text = downloader::get_text(&self.http, &url, headers).await?;
+            if let Some(found) = checksum::find(&text, filename, sibling) {
+                debug!("Found checksum for {} in {}", filename, asset.name);
+                return Ok(found);
+            }
 
-        for (hash_type, algorithm) in hash_types {
-            let checksum_asset_idx = assets_with_same_basename
-                .iter()
-                .position(|a| a.starts_with(basename) && a.ends_with(hash_type));
+            searched.push(asset.name.clone());
+        }
 
-            if checksum_asset_idx.is_none() {
-                continue;
+        if let Some(body) = &release.body {
+            if let Some(found) = checksum::find(body, filename, false) {
+                debug!("Found checksum for {} in the release notes", filename);
+                return Ok(found);
             }
 
-            let checksum_url = release
-                .assets
-                .get(checksum_asset_idx.unwrap())
-                .unwrap()
-                .browser_download_url
-                .clone();
+            searched.push("the release notes".to_string());
+        }
 
-            let response = reqwest::get(checksum_url).await?;
+        match searched.is_empty() {
+            true => Err(Error::NotFound(format!(
+                "Could not find a checksum for {} in release {}",
+                filename, release.tag_name
+            ))),
+            false => Err(Error::NotFound(format!(
+                "No checksum for {} in {}",
+                filename,
+                searched.join(", ")
+            ))),
+        }
+    }
+
+    /// Picks the asset to install, asking the asset chooser if several match equally well.
+    fn get_asset(&self, release: &Release) -> Result<Asset, Error> {
+        let mut candidates = self.find_assets(release)?;
+        if candidates.len() == 1 {
+            return Ok(candidates.remove(0));
+        }
 
-            let checksum = response
-                .text()
-                .await?
-                .split_whitespace()
-                .next()
-                .unwrap()
-                .to_string();
+        let names = candidates.iter().map(|a| a.name.clone()).collect();
+        let chooser = self
+            .asset_chooser
+            .as_ref()
+            .ok_or_else(|| Error::AmbiguousAsset(names))?;
+        match chooser(release, &candidates) {
+            Some(index) if index < candidates.len() => Ok(candidates.remove(index)),
+            _ => Err(Error::NotFound("No asset was chosen".to_string())),
+        }
+    }
+
+    /// Returns the archives in a release that match `asset_pattern` and none of the
+    /// `exclude_patterns`, narrowed down by `asset_preference` and then by archive type.
+    fn find_assets(&self, release: &Release) -> Result<Vec<Asset>, Error> {
+        let pattern = self
+            .config
+            .asset_pattern
+            .as_deref()
+            .map(NamePattern::parse)
+            .transpose()?;
+        let excludes = self
+            .config
+            .exclude_patterns
+            .iter()
+            .map(|p| NamePattern::parse(p))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let matching: Vec<&Asset> = release
+            .assets
+            .iter()
+            .filter(|a| pattern.as_ref().is_none_or(|p| p.matches(&a.name)))
+            .filter(|a| !excludes.iter().any(|p| p.matches(&a.name)))
+            .collect();
+        let mut candidates: Vec<Asset> = matching
+            .iter()
+            .filter(|a| extract::archive_stem(&a.name).is_some())
+            .map(|a| (*a).clone())
+            .collect();
 
-            return Ok((checksum, algorithm));
+        // An asset with an unusual name can still be installed by matching it with
+        // `asset_pattern`, as archives are recognized by their contents.
+        if candidates.is_empty() && pattern.is_some() {
+            candidates = matching
+                .into_iter()
+                .filter(|a| !is_metadata_file(release, &a.name))
+                .cloned()
+                .collect();
         }
 
-        Err(Error::NotFound(
-            "Could not find a checksum file in the release".to_string(),
-        ))
+        if candidates.is_empty() {
+            return Err(Error::NotFound(format!(
+                "No supported asset in release {} matches the configured patterns",
+                release.tag_name
+            )));
+        }
+
+        let preferences = self
+            .config
+            .asset_preference
+            .iter()
+            .map(String::as_str)
+            .chain(DEFAULT_ASSET_PREFERENCE);
+        for preference in preferences {
+            if candidates.len() == 1 {
+                break;
+            }
+
+            let preference = NamePattern::parse(preference)?;
+            if candidates.iter().any(|a| preference.matches(&a.name)) {
+                candidates.retain(|a| preference.matches(&a.name));
+            }
+        }
+
+        Ok(candidates)
     }
+}
 
-    async fn get_asset(&self, release: &Release) -> Result<Asset, Error> {
-        let asset_types = vec!["tar.gz", "tar.xz"];
+/// Releases fetched page by page, see [`ProtonManager::stream_releases`].
+pub struct ReleaseStream<'a> {
+    manager: &'a mut ProtonManager,
+    per_page: u8,
+    cursor: Option<PageCursor>,
+    buffer: std::vec::IntoIter<Release>,
+}
 
-        for asset_type in asset_types {
-            let asset = release
-                .assets
-                .iter()
-                .find(|a| a.name.ends_with(asset_type))
-                .cloned();
+impl ReleaseStream<'_> {
+    /// Returns the next release in the module's channel, or `None` once the last page has been
+    /// read.
+    pub async fn next(&mut self) -> Result<Option<Release>, Error> {
+        loop {
+            let channel = self.manager.config.channel;
+            if let Some(release) = self.buffer.find(|r| channel.includes(r)) {
+                return Ok(Some(release));
+            }
 
-            if asset.is_some() {
-                return Ok(asset.unwrap());
+            let cursor = match self.cursor.take() {
+                Some(cursor) => cursor,
+                None => return Ok(None),
+            };
+
+            debug!("Fetching page {:?}", cursor);
+            let page = self
+                .manager
+                .source
+                .list_page(&cursor, self.per_page)
+                .await?;
+            if page.releases.is_empty() {
+                return Ok(None);
             }
+
+            let releases = self.manager.remember_releases(page.releases);
+            self.cursor = page.next;
+            self.buffer = releases.into_iter();
         }
+    }
+}
+
+/// Returns the paths a release was extracted to, falling back to a directory named after the tag
+/// for releases installed before their contents were recorded.
+fn get_installed_paths(release: &Release) -> Vec<PathBuf> {
+    let install_dir = match &release.installed_in {
+        Some(dir) => dir,
+        None => return Vec::new(),
+    };
 
-        Err(Error::NotFound(
-            "Could not find a supported asset in the release".to_string(),
-        ))
+    if release.installed_contents.is_empty() {
+        return vec![install_dir.join(&release.tag_name)];
     }
+
+    release
+        .installed_contents
+        .iter()
+        .map(|c| install_dir.join(c))
+        .collect()
+}
+
+/// Whether `name` is a checksum file or a signature of another asset in the release.
+fn is_metadata_file(release: &Release, name: &str) -> bool {
+    checksum::is_checksum_file(name)
+        || release
+            .assets
+            .iter()
+            .any(|a| signature::signature_kind(name, &a.name).is_some())
 }
@@ -0,0 +1,113 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::config::ConfigModule;
+use crate::error::Error;
+use crate::models::release::Release;
+use crate::sources::{
+    api_url, download_asset, get_api_release, list_api_page, required_setting, PageCursor,
+    ReleasePage, ReleaseSource,
+};
+use crate::utilities::http::HttpClient;
+
+const DEFAULT_HOST: &str = "https://codeberg.org";
+
+/// Releases from a Gitea or Forgejo instance, such as Codeberg.
+pub struct GiteaSource {
+    host: String,
+    owner: String,
+    repo: String,
+    http: HttpClient,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaRelease {
+    name: Option<String>,
+    tag_name: String,
+    created_at: Option<DateTime<Utc>>,
+    published_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    draft: bool,
+    body: Option<String>,
+    #[serde(default)]
+    assets: Vec<GiteaAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GiteaAsset {
+    name: String,
+    browser_download_url: Url,
+    created_at: DateTime<Utc>,
+}
+
+impl From<GiteaRelease> for Release {
+    fn from(release: GiteaRelease) -> Self {
+        Self {
+            name: release.name,
+            tag_name: release.tag_name,
+            created_at: release.created_at,
+            published_at: release.published_at.or(release.created_at),
+            assets: release
+                .assets
+                .into_iter()
+                .map(|asset| {
+                    download_asset(asset.name, asset.browser_download_url, asset.created_at)
+                })
+                .collect(),
+            prerelease: release.prerelease,
+            draft: release.draft,
+            body: release.body,
+            installed_in: None,
+            installed_contents: Vec::new(),
+        }
+    }
+}
+
+impl GiteaSource {
+    pub fn new(config: &ConfigModule, client: &HttpClient) -> Result<Self, Error> {
+        let host = config
+            .host
+            .clone()
+            .unwrap_or_else(|| DEFAULT_HOST.to_string());
+        api_url(&host, &[])?;
+
+        Ok(Self {
+            host,
+            owner: required_setting(Some(config.owner.clone()), "owner")?,
+            repo: required_setting(Some(config.repo.clone()), "repo")?,
+            http: client.clone(),
+        })
+    }
+
+    /// Builds `{host}/api/v1/repos/{owner}/{repo}/releases/{segments}`.
+    fn releases_url(&self, segments: &[&str]) -> Result<Url, Error> {
+        let path = ["api", "v1", "repos", &self.owner, &self.repo, "releases"];
+        api_url(&self.host, &[&path[..], segments].concat())
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for GiteaSource {
+    async fn list_page(&self, cursor: &PageCursor, per_page: u8) -> Result<ReleasePage, Error> {
+        let url = self.releases_url(&[])?;
+        list_api_page::<GiteaRelease>(&self.http, url, cursor, "limit", per_page).await
+    }
+
+    async fn get_release(&self, tag: &str) -> Result<Release, Error> {
+        let url = self.releases_url(&["tags", tag])?;
+        get_api_release::<GiteaRelease>(&self.http, url, tag).await
+    }
+
+    fn location(&self) -> String {
+        format!(
+            "{}/{}/{}",
+            self.host.trim_end_matches('/'),
+            self.owner,
+            self.repo
+        )
+    }
+}
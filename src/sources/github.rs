@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION};
+use reqwest::Url;
+
+use crate::config::ConfigModule;
+use crate::error::Error;
+use crate::models::asset::Asset;
+use crate::models::release::Release;
+use crate::sources::{required_setting, PageCursor, ReleasePage, ReleaseSource};
+use crate::utilities::api_client::ApiClient;
+use crate::utilities::http::HttpClient;
+use crate::utilities::token;
+
+const DEFAULT_HOST: &str = "github.com";
+const DEFAULT_API_BASE: &str = "https://api.github.com/";
+
+/// Releases from GitHub or GitHub Enterprise. Requests go through the shared [`HttpClient`], so
+/// they use the configured proxies and certificates; octocrab only provides the response models.
+pub struct GitHubSource {
+    http: HttpClient,
+    api: ApiClient,
+    api_base: Url,
+    owner: String,
+    repo: String,
+    token: Option<String>,
+}
+
+impl GitHubSource {
+    pub fn new(
+        config: &ConfigModule,
+        cache_dir: &Path,
+        client: &HttpClient,
+    ) -> Result<Self, Error> {
+        let api_base = parse_base(config.api_base.as_deref().unwrap_or(DEFAULT_API_BASE))?;
+        // Tokens for api.github.com are filed under github.com by the gh CLI.
+        let host = match api_base.host_str() {
+            Some("api.github.com") | None => DEFAULT_HOST.to_string(),
+            Some(host) => host.to_string(),
+        };
+        let token = token::discover_github_token(config, &host);
+
+        // pup never uploads assets, so the uploads endpoint is only validated. It is accepted so
+        // GitHub Enterprise configs can be shared with tools that do.
+        if let Some(uploads_base) = &config.uploads_base {
+            parse_base(uploads_base)?;
+        }
+
+        Ok(Self {
+            http: client.clone(),
+            api: ApiClient::new(cache_dir.join("github-responses.json"), client.clone()),
+            api_base,
+            owner: required_setting(Some(config.owner.clone()), "owner")?,
+            repo: required_setting(Some(config.repo.clone()), "repo")?,
+            token,
+        })
+    }
+
+    fn releases_url(&self, path: &str) -> Result<Url, Error> {
+        self.api_base
+            .join(&format!(
+                "repos/{}/{}/releases{}",
+                self.owner, self.repo, path
+            ))
+            .map_err(|e| Error::Url(e.to_string()))
+    }
+
+    /// Fetches and deserializes `url`, returning the next page's URL for paginated endpoints.
+    async fn get<T: serde::de::DeserializeOwned>(
+        &self,
+        url: &Url,
+    ) -> Result<(T, Option<Url>), Error> {
+        let request = self.http.get(url.clone()).headers(self.auth_headers());
+        let response = self.api.get(url, request).await?;
+        Ok((serde_json::from_str(&response.body)?, response.next))
+    }
+
+    /// The `Authorization` header for the token, if there is one.
+    fn auth_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &self.token {
+            if let Ok(mut value) = HeaderValue::from_str(&format!("Bearer {}", token)) {
+                value.set_sensitive(true);
+                headers.insert(AUTHORIZATION, value);
+            }
+        }
+
+        headers
+    }
+}
+
+/// Parses a base URL, adding the trailing slash that relative API routes are joined onto.
+fn parse_base(base: &str) -> Result<Url, Error> {
+    let mut base = base.to_string();
+    if !base.ends_with('/') {
+        base.push('/');
+    }
+
+    Url::parse(&base).map_err(|e| Error::Url(e.to_string()))
+}
+
+#[async_trait]
+impl ReleaseSource for GitHubSource {
+    async fn list_page(&self, cursor: &PageCursor, per_page: u8) -> Result<ReleasePage, Error> {
+        let url = match cursor {
+            PageCursor::Number(number) => {
+                let mut url = self.releases_url("")?;
+                url.query_pairs_mut()
+                    .append_pair("per_page", &per_page.to_string())
+                    .append_pair("page", &number.to_string());
+                url
+            }
+            PageCursor::Url(url) => url.clone(),
+        };
+
+        let (releases, next) = self
+            .get::<Vec<octocrab::models::repos::Release>>(&url)
+            .await?;
+
+        Ok(ReleasePage {
+            releases: releases.into_iter().map(Release::from).collect(),
+            next: next.map(PageCursor::Url),
+        })
+    }
+
+    async fn get_release(&self, tag: &str) -> Result<Release, Error> {
+        let url = self.releases_url(&format!("/tags/{}", tag))?;
+        let (release, _) = self
+            .get::<octocrab::models::repos::Release>(&url)
+            .await
+            .map_err(|e| match e {
+                Error::NotFound(_) => Error::NotFound(format!("release {}", tag)),
+                e => e,
+            })?;
+
+        Ok(release.into())
+    }
+
+    fn location(&self) -> String {
+        format!("@{}/{}", self.owner, self.repo)
+    }
+
+    /// With a token, assets are fetched through the API so that private assets work too.
+    fn asset_request(&self, asset: &Asset) -> (Url, HeaderMap) {
+        match (&self.token, &asset.api_url) {
+            (Some(_), Some(api_url)) => {
+                let mut headers = self.auth_headers();
+                headers.insert(ACCEPT, HeaderValue::from_static("application/octet-stream"));
+                (api_url.clone(), headers)
+            }
+            _ => (asset.browser_download_url.clone(), HeaderMap::new()),
+        }
+    }
+}
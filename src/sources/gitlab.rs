@@ -0,0 +1,126 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::config::ConfigModule;
+use crate::error::Error;
+use crate::models::release::Release;
+use crate::sources::{
+    api_url, download_asset, get_api_release, list_api_page, required_setting, PageCursor,
+    ReleasePage, ReleaseSource,
+};
+use crate::utilities::http::HttpClient;
+
+const DEFAULT_HOST: &str = "https://gitlab.com";
+
+pub struct GitLabSource {
+    host: String,
+    project: String,
+    http: HttpClient,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabRelease {
+    name: Option<String>,
+    tag_name: String,
+    created_at: Option<DateTime<Utc>>,
+    released_at: Option<DateTime<Utc>>,
+    description: Option<String>,
+    /// Set for releases whose release date is in the future.
+    #[serde(default)]
+    upcoming_release: bool,
+    assets: GitLabAssets,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabAssets {
+    #[serde(default)]
+    links: Vec<GitLabLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GitLabLink {
+    name: String,
+    url: Url,
+    direct_asset_url: Option<Url>,
+}
+
+impl From<GitLabRelease> for Release {
+    fn from(release: GitLabRelease) -> Self {
+        let published_at = release.released_at.or(release.created_at);
+        let updated_at = published_at.unwrap_or_default();
+
+        Self {
+            name: release.name,
+            tag_name: release.tag_name,
+            created_at: release.created_at,
+            published_at,
+            assets: release
+                .assets
+                .links
+                .into_iter()
+                .map(|link| {
+                    download_asset(
+                        link.name,
+                        link.direct_asset_url.unwrap_or(link.url),
+                        updated_at,
+                    )
+                })
+                .collect(),
+            prerelease: release.upcoming_release,
+            draft: false,
+            body: release.description,
+            installed_in: None,
+            installed_contents: Vec::new(),
+        }
+    }
+}
+
+impl GitLabSource {
+    pub fn new(config: &ConfigModule, client: &HttpClient) -> Result<Self, Error> {
+        let project = match &config.project {
+            Some(project) => required_setting(Some(project.clone()), "project")?,
+            None => format!(
+                "{}/{}",
+                required_setting(Some(&config.owner), "owner")?,
+                required_setting(Some(&config.repo), "repo")?
+            ),
+        };
+        let host = config
+            .host
+            .clone()
+            .unwrap_or_else(|| DEFAULT_HOST.to_string());
+        api_url(&host, &[])?;
+
+        Ok(Self {
+            host,
+            project,
+            http: client.clone(),
+        })
+    }
+
+    /// Builds `{host}/api/v4/projects/{project}/releases/{segments}`, encoding the project path
+    /// as a single segment as the API requires.
+    fn releases_url(&self, segments: &[&str]) -> Result<Url, Error> {
+        let path = ["api", "v4", "projects", &self.project, "releases"];
+        api_url(&self.host, &[&path[..], segments].concat())
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for GitLabSource {
+    async fn list_page(&self, cursor: &PageCursor, per_page: u8) -> Result<ReleasePage, Error> {
+        let url = self.releases_url(&[])?;
+        list_api_page::<GitLabRelease>(&self.http, url, cursor, "per_page", per_page).await
+    }
+
+    async fn get_release(&self, tag: &str) -> Result<Release, Error> {
+        let url = self.releases_url(&[tag])?;
+        get_api_release::<GitLabRelease>(&self.http, url, tag).await
+    }
+
+    fn location(&self) -> String {
+        format!("{}/{}", self.host.trim_end_matches('/'), self.project)
+    }
+}
@@ -0,0 +1,271 @@
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use crate::config::ConfigModule;
+use crate::error::Error;
+use crate::models::release::Release;
+use crate::sources::{
+    archive_releases, directory_release, download_asset, new_release, page_number,
+    release_directories, required_setting, slice_page, ListingEntry, PageCursor, ReleasePage,
+    ReleaseSource,
+};
+use crate::utilities::http::HttpClient;
+
+const MANIFEST: &str = "releases.json";
+const LISTING_DATE_FORMATS: [&str; 2] = ["%d-%b-%Y %H:%M", "%Y-%m-%d %H:%M"];
+
+/// Releases from a plain web server, described either by a `releases.json` manifest or by the
+/// server's directory listing.
+pub struct HttpSource {
+    base_url: String,
+    http: HttpClient,
+    /// What the pages listed so far have read, for the following pages to start from.
+    read: Mutex<Option<ReadReleases>>,
+}
+
+/// The releases read from the server so far, newest first.
+struct ReadReleases {
+    releases: Vec<Release>,
+    /// Subdirectories of the listing that may hold a release, newest first.
+    directories: Vec<ListingEntry>,
+    /// How many of `directories` have been looked into.
+    directories_read: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestRelease {
+    name: Option<String>,
+    tag_name: String,
+    published_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    prerelease: bool,
+    #[serde(default)]
+    assets: Vec<ManifestAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestAsset {
+    name: String,
+    /// Absolute, or relative to the base URL. Defaults to the asset name.
+    url: Option<String>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl ManifestRelease {
+    fn into_release(self, base: &Url) -> Result<Release, Error> {
+        let published_at = self.published_at;
+        let assets = self
+            .assets
+            .into_iter()
+            .map(|asset| {
+                let url = join(base, asset.url.as_deref().unwrap_or(&asset.name))?;
+                let updated_at = asset.updated_at.or(published_at).unwrap_or_default();
+                Ok(download_asset(asset.name, url, updated_at))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let mut release = new_release(self.name, self.tag_name, published_at, assets);
+        release.prerelease = self.prerelease;
+        Ok(release)
+    }
+}
+
+impl HttpSource {
+    pub fn new(config: &ConfigModule, client: &HttpClient) -> Result<Self, Error> {
+        let source = Self {
+            base_url: required_setting(config.host.clone(), "host")?,
+            http: client.clone(),
+            read: Mutex::new(None),
+        };
+        source.base()?;
+        Ok(source)
+    }
+
+    fn base(&self) -> Result<Url, Error> {
+        let mut base_url = self.base_url.clone();
+        if !base_url.ends_with('/') {
+            base_url.push('/');
+        }
+
+        Url::parse(&base_url).map_err(|e| Error::Url(e.to_string()))
+    }
+
+    /// Reads releases from the manifest if there is one, or from the top level of the directory
+    /// listing otherwise, optionally restricted to a single tag. Subdirectories of the listing are
+    /// only looked into by [`Self::read_directories`].
+    async fn read_index(&self, tag: Option<&str>) -> Result<ReadReleases, Error> {
+        let base = self.base()?;
+        let response = self
+            .http
+            .send(self.http.get(join(&base, MANIFEST)?))
+            .await?;
+
+        let (releases, directories) = if response.status() == StatusCode::NOT_FOUND {
+            debug!(
+                "No manifest found, reading the directory listing at {}",
+                base
+            );
+            let entries = fetch_listing(&self.http, &base).await?;
+            let directories = release_directories(&entries, tag)
+                .into_iter()
+                .cloned()
+                .collect();
+            (archive_releases(&entries, tag), directories)
+        } else {
+            let releases = response
+                .error_for_status()?
+                .json::<Vec<ManifestRelease>>()
+                .await?
+                .into_iter()
+                .filter(|r| tag.is_none_or(|tag| r.tag_name == tag))
+                .map(|r| r.into_release(&base))
+                .collect::<Result<Vec<_>, Error>>()?;
+            (releases, Vec::new())
+        };
+
+        let mut read = ReadReleases {
+            releases,
+            directories,
+            directories_read: 0,
+        };
+        read.releases.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        Ok(read)
+    }
+
+    /// Looks into the newest subdirectories of the listing until `count` of them have been read,
+    /// adding the releases they hold.
+    async fn read_directories(&self, read: &mut ReadReleases, count: usize) -> Result<(), Error> {
+        let end = count.min(read.directories.len());
+        if read.directories_read >= end {
+            return Ok(());
+        }
+
+        while read.directories_read < end {
+            let directory = &read.directories[read.directories_read];
+            let files = fetch_listing(&self.http, &directory.url).await?;
+            read.releases.extend(directory_release(directory, &files));
+            read.directories_read += 1;
+        }
+        read.releases.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for HttpSource {
+    async fn list_page(&self, cursor: &PageCursor, per_page: u8) -> Result<ReleasePage, Error> {
+        let number = page_number(cursor)?;
+        // One more than the page holds, to tell whether there is a next page.
+        let count = number as usize * per_page as usize + 1;
+
+        // Later pages carry on from what the previous ones read. The first page reads the server
+        // again, so each new listing sees its current releases.
+        let mut read = self.read.lock().await;
+        let read = match read.take() {
+            Some(previous) if number > 1 => read.insert(previous),
+            _ => read.insert(self.read_index(None).await?),
+        };
+        self.read_directories(read, count).await?;
+
+        let releases = read.releases.iter().take(count).cloned().collect();
+        Ok(slice_page(releases, number, per_page))
+    }
+
+    async fn get_release(&self, tag: &str) -> Result<Release, Error> {
+        let mut read = self.read_index(Some(tag)).await?;
+        self.read_directories(&mut read, 1).await?;
+        read.releases
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::NotFound(format!("release {}", tag)))
+    }
+
+    fn location(&self) -> String {
+        self.base_url.clone()
+    }
+}
+
+fn join(base: &Url, path: &str) -> Result<Url, Error> {
+    base.join(path).map_err(|e| Error::Url(e.to_string()))
+}
+
+async fn fetch_listing(client: &HttpClient, url: &Url) -> Result<Vec<ListingEntry>, Error> {
+    let html = client
+        .send(client.get(url.clone()))
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(parse_listing(&html, url))
+}
+
+/// Extracts the entries from an autoindex page at `base`, as generated by nginx, Apache or
+/// lighttpd. Modification times are read from the text following each link when present.
+fn parse_listing(html: &str, base: &Url) -> Vec<ListingEntry> {
+    let mut entries = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find("href=\"") {
+        rest = &rest[start + "href=\"".len()..];
+        let end = match rest.find('"') {
+            Some(end) => end,
+            None => break,
+        };
+
+        let href = &rest[..end];
+        rest = &rest[end..];
+        if href.is_empty()
+            || href.starts_with(['?', '#', '/', '.'])
+            || href.contains("://")
+            || href.contains('?')
+        {
+            continue;
+        }
+
+        let url = match base.join(href) {
+            Ok(url) => url,
+            Err(_) => continue,
+        };
+
+        let text = &rest[..rest.find("href=\"").unwrap_or(rest.len())];
+        let is_dir = href.ends_with('/');
+        entries.push(ListingEntry {
+            url,
+            name: href.trim_end_matches('/').to_string(),
+            is_dir,
+            modified: parse_listing_date(text),
+        });
+    }
+
+    entries
+}
+
+fn parse_listing_date(html: &str) -> Option<DateTime<Utc>> {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    let tokens = text.split_whitespace().collect::<Vec<_>>();
+    tokens.windows(2).find_map(|pair| {
+        let candidate = pair.join(" ");
+        LISTING_DATE_FORMATS.iter().find_map(|format| {
+            NaiveDateTime::parse_from_str(&candidate, format)
+                .ok()
+                .map(|date| Utc.from_utc_datetime(&date))
+        })
+    })
+}
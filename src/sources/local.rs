@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+
+use crate::config::ConfigModule;
+use crate::error::Error;
+use crate::models::release::Release;
+use crate::sources::{
+    archive_releases, directory_release, page_number, release_directories, required_setting,
+    slice_page, ListingEntry, PageCursor, ReleasePage, ReleaseSource,
+};
+
+/// Releases from a local folder of pre-downloaded archives, laid out like an HTTP mirror: each
+/// archive is a release with its sibling checksum files, and each subdirectory containing an
+/// archive is a release named after the directory.
+pub struct LocalSource {
+    local_dir: PathBuf,
+}
+
+impl LocalSource {
+    pub fn new(config: &ConfigModule) -> Result<Self, Error> {
+        Ok(Self {
+            local_dir: required_setting(config.local_dir.clone(), "local_dir")?,
+        })
+    }
+
+    fn read_releases(&self, tag: Option<&str>) -> Result<Vec<Release>, Error> {
+        let local_dir = self.local_dir.canonicalize().map_err(|e| {
+            Error::NotFound(format!(
+                "local directory {}: {}",
+                self.local_dir.display(),
+                e
+            ))
+        })?;
+        let entries = read_dir(&local_dir)?;
+
+        let mut releases = archive_releases(&entries, tag);
+        for directory in release_directories(&entries, tag) {
+            let path = directory.url.to_file_path().unwrap_or_default();
+            releases.extend(directory_release(directory, &read_dir(&path)?));
+        }
+
+        releases.sort_by(|a, b| b.partial_cmp(a).unwrap());
+        Ok(releases)
+    }
+}
+
+#[async_trait]
+impl ReleaseSource for LocalSource {
+    async fn list_page(&self, cursor: &PageCursor, per_page: u8) -> Result<ReleasePage, Error> {
+        let number = page_number(cursor)?;
+        Ok(slice_page(self.read_releases(None)?, number, per_page))
+    }
+
+    async fn get_release(&self, tag: &str) -> Result<Release, Error> {
+        self.read_releases(Some(tag))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::NotFound(format!("release {}", tag)))
+    }
+
+    fn location(&self) -> String {
+        self.local_dir.display().to_string()
+    }
+}
+
+fn read_dir(dir: &Path) -> Result<Vec<ListingEntry>, Error> {
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        let name = match entry.file_name().to_str() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let path = entry.path();
+        let url = Url::from_file_path(&path).map_err(|_| Error::Url(path.display().to_string()))?;
+
+        files.push(ListingEntry {
+            name,
+            url,
+            is_dir: metadata.is_dir(),
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+        });
+    }
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
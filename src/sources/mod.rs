@@ -0,0 +1,286 @@
+use std::path::Path;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use reqwest::{StatusCode, Url};
+use serde::de::DeserializeOwned;
+
+use crate::config::{ConfigModule, SourceType};
+use crate::error::Error;
+use crate::models::asset::Asset;
+use crate::models::release::Release;
+use crate::utilities::api_client;
+use crate::utilities::extract;
+use crate::utilities::http::HttpClient;
+
+pub mod gitea;
+pub mod github;
+pub mod gitlab;
+pub mod http;
+pub mod local;
+
+/// Where a page of releases starts: a page number, or the `next` link of the previous page.
+#[derive(Debug, Clone)]
+pub enum PageCursor {
+    Number(u32),
+    Url(Url),
+}
+
+/// A page of releases, newest first.
+pub struct ReleasePage {
+    pub releases: Vec<Release>,
+    pub next: Option<PageCursor>,
+}
+
+/// A place releases can be listed and fetched from.
+#[async_trait]
+pub trait ReleaseSource: Send + Sync {
+    /// Lists the most recent releases, newest first.
+    async fn list_releases(&self, count: u8) -> Result<Vec<Release>, Error> {
+        Ok(self
+            .list_page(&PageCursor::Number(1), count)
+            .await?
+            .releases)
+    }
+
+    /// Lists a page of up to `per_page` releases. Sources without server-side pagination can
+    /// read their releases and cut a page out of them with [`slice_page`].
+    async fn list_page(&self, cursor: &PageCursor, per_page: u8) -> Result<ReleasePage, Error>;
+
+    async fn get_release(&self, tag: &str) -> Result<Release, Error>;
+
+    /// A human-readable description of where the releases come from.
+    fn location(&self) -> String;
+
+    /// The URL and headers to download an asset with.
+    fn asset_request(&self, asset: &Asset) -> (Url, HeaderMap) {
+        (asset.browser_download_url.clone(), HeaderMap::new())
+    }
+}
+
+/// Creates the source configured for a module. `cache_dir` is the module's own cache directory,
+/// where sources may keep state between runs. Sources send their requests through `client`.
+pub fn from_config(
+    config: &ConfigModule,
+    cache_dir: &Path,
+    client: &HttpClient,
+) -> Result<Box<dyn ReleaseSource>, Error> {
+    let source: Box<dyn ReleaseSource> = match config.source {
+        SourceType::GitHub => Box::new(github::GitHubSource::new(config, cache_dir, client)?),
+        SourceType::GitLab => Box::new(gitlab::GitLabSource::new(config, client)?),
+        SourceType::Gitea => Box::new(gitea::GiteaSource::new(config, client)?),
+        SourceType::Http => Box::new(http::HttpSource::new(config, client)?),
+        SourceType::Local => Box::new(local::LocalSource::new(config)?),
+    };
+
+    Ok(source)
+}
+
+/// The value of `setting`, which the source cannot work without. Empty strings count as unset.
+pub(crate) fn required_setting<T: AsRef<std::ffi::OsStr>>(
+    value: Option<T>,
+    setting: &str,
+) -> Result<T, Error> {
+    value
+        .filter(|value| !value.as_ref().is_empty())
+        .ok_or_else(|| Error::Config(config::ConfigError::NotFound(setting.to_string())))
+}
+
+/// The page number `cursor` points to, for sources that number their pages themselves.
+pub(crate) fn page_number(cursor: &PageCursor) -> Result<u32, Error> {
+    match cursor {
+        PageCursor::Number(number) => Ok((*number).max(1)),
+        PageCursor::Url(url) => Err(Error::Url(url.to_string())),
+    }
+}
+
+/// Cuts page `number` of `per_page` releases out of `releases`, which must hold at least one
+/// release past the page for the next page to be found.
+pub(crate) fn slice_page(releases: Vec<Release>, number: u32, per_page: u8) -> ReleasePage {
+    let start = (number as usize - 1) * per_page as usize;
+    let end = start + per_page as usize;
+    let next = (releases.len() > end).then_some(PageCursor::Number(number + 1));
+
+    ReleasePage {
+        releases: releases
+            .into_iter()
+            .skip(start)
+            .take(per_page as usize)
+            .collect(),
+        next,
+    }
+}
+
+/// Builds `{host}/{segments}`, keeping any path `host` already has.
+pub(crate) fn api_url(host: &str, segments: &[&str]) -> Result<Url, Error> {
+    let mut url = Url::parse(host).map_err(|e| Error::Url(e.to_string()))?;
+    url.path_segments_mut()
+        .map_err(|_| Error::Url(host.to_string()))?
+        .pop_if_empty()
+        .extend(segments);
+    Ok(url)
+}
+
+/// Lists a page of releases from a Gitea or GitLab style API at `url`, which takes the page
+/// size as `per_page_param` and links to the next page in the `Link` header.
+pub(crate) async fn list_api_page<T: DeserializeOwned + Into<Release>>(
+    http: &HttpClient,
+    url: Url,
+    cursor: &PageCursor,
+    per_page_param: &str,
+    per_page: u8,
+) -> Result<ReleasePage, Error> {
+    let request = match cursor {
+        PageCursor::Number(number) => http
+            .get(url)
+            .query(&[(per_page_param, per_page as u32), ("page", *number)]),
+        PageCursor::Url(url) => http.get(url.clone()),
+    };
+
+    let response = http.send(request).await?.error_for_status()?;
+    let next = api_client::get_next_link(response.headers()).map(PageCursor::Url);
+    let releases = response
+        .json::<Vec<T>>()
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(ReleasePage { releases, next })
+}
+
+/// Fetches release `tag` from a Gitea or GitLab style API at `url`, which answers unknown tags
+/// with a 404.
+pub(crate) async fn get_api_release<T: DeserializeOwned + Into<Release>>(
+    http: &HttpClient,
+    url: Url,
+    tag: &str,
+) -> Result<Release, Error> {
+    let response = http.send(http.get(url)).await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(Error::NotFound(format!("release {}", tag)));
+    }
+
+    Ok(response.error_for_status()?.json::<T>().await?.into())
+}
+
+/// An asset downloaded straight from `url`, without going through an API.
+pub(crate) fn download_asset(name: String, url: Url, updated_at: DateTime<Utc>) -> Asset {
+    Asset {
+        name,
+        browser_download_url: url,
+        updated_at,
+        api_url: None,
+    }
+}
+
+/// A file or directory in a folder of releases, such as a local directory or the directory
+/// listing of a web server.
+#[derive(Clone)]
+pub(crate) struct ListingEntry {
+    pub name: String,
+    pub url: Url,
+    pub is_dir: bool,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+impl ListingEntry {
+    fn to_asset(&self) -> Asset {
+        download_asset(
+            self.name.clone(),
+            self.url.clone(),
+            self.modified.unwrap_or_default(),
+        )
+    }
+}
+
+/// Archives at the top level of a listing are releases of their own, with any sibling files
+/// sharing their name (such as `*.sha512sum`) as extra assets. With `tag`, only that release is
+/// returned.
+pub(crate) fn archive_releases(entries: &[ListingEntry], tag: Option<&str>) -> Vec<Release> {
+    let mut releases = Vec::new();
+    for entry in entries.iter().filter(|e| !e.is_dir) {
+        let stem = match extract::archive_stem(&entry.name) {
+            Some(stem) if tag.is_none_or(|tag| stem == tag) => stem,
+            _ => continue,
+        };
+
+        let assets = entries
+            .iter()
+            .filter(|e| !e.is_dir && is_sibling(&e.name, stem))
+            .map(ListingEntry::to_asset)
+            .collect();
+        releases.push(new_release(None, stem.to_string(), entry.modified, assets));
+    }
+
+    releases
+}
+
+/// The subdirectories of a listing that may hold a release, newest first. With `tag`, only the
+/// directory named after it is returned.
+pub(crate) fn release_directories<'a>(
+    entries: &'a [ListingEntry],
+    tag: Option<&str>,
+) -> Vec<&'a ListingEntry> {
+    let mut directories = entries
+        .iter()
+        .filter(|e| e.is_dir && tag.is_none_or(|tag| e.name == tag))
+        .collect::<Vec<_>>();
+    directories.sort_by_key(|d| std::cmp::Reverse(d.modified));
+    directories
+}
+
+/// A release named after `directory`, with the `files` inside as assets, if one of them is an
+/// archive.
+pub(crate) fn directory_release(
+    directory: &ListingEntry,
+    files: &[ListingEntry],
+) -> Option<Release> {
+    if !files
+        .iter()
+        .any(|f| extract::archive_stem(&f.name).is_some())
+    {
+        return None;
+    }
+
+    let assets = files
+        .iter()
+        .filter(|f| !f.is_dir)
+        .map(ListingEntry::to_asset)
+        .collect();
+    Some(new_release(
+        None,
+        directory.name.clone(),
+        directory.modified,
+        assets,
+    ))
+}
+
+/// Builds a release for sources that only know a tag, a date and a list of files.
+pub(crate) fn new_release(
+    name: Option<String>,
+    tag_name: String,
+    published_at: Option<DateTime<Utc>>,
+    assets: Vec<Asset>,
+) -> Release {
+    Release {
+        name,
+        tag_name,
+        created_at: published_at,
+        published_at,
+        assets,
+        prerelease: false,
+        draft: false,
+        body: None,
+        installed_in: None,
+        installed_contents: Vec::new(),
+    }
+}
+
+/// Whether `name` is `stem` itself or `stem` followed by an extension, e.g. the checksum file
+/// `GE-Proton9-1.sha512sum` next to `GE-Proton9-1.tar.gz`.
+pub(crate) fn is_sibling(name: &str, stem: &str) -> bool {
+    name.strip_prefix(stem)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
@@ -0,0 +1,231 @@
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, TimeZone, Utc};
+use reqwest::header::{
+    AsHeaderName, HeaderMap, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, LINK,
+};
+use reqwest::{RequestBuilder, Response, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::utilities::cache::Cache;
+use crate::utilities::http;
+use crate::utilities::http::HttpClient;
+
+/// The longest pup will sleep for a rate limit to reset before giving up.
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+const CACHED_RESPONSES: usize = 20;
+
+/// A response body stored with the validators needed to revalidate it.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct CachedResponse {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+    #[serde(default)]
+    next: Option<Url>,
+    fetched_at: DateTime<Utc>,
+}
+
+/// The parts of an API response pup uses.
+pub struct ApiResponse {
+    pub body: String,
+    /// The `rel="next"` URL from the `Link` header, for paginated responses.
+    pub next: Option<Url>,
+}
+
+impl From<CachedResponse> for ApiResponse {
+    fn from(cached: CachedResponse) -> Self {
+        Self {
+            body: cached.body,
+            next: cached.next,
+        }
+    }
+}
+
+impl PartialEq for CachedResponse {
+    fn eq(&self, other: &Self) -> bool {
+        self.url == other.url
+    }
+}
+
+impl Eq for CachedResponse {}
+
+impl Hash for CachedResponse {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.url.hash(state);
+    }
+}
+
+impl PartialOrd for CachedResponse {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.fetched_at.partial_cmp(&other.fetched_at)
+    }
+}
+
+/// Sends API requests conditionally, so unchanged data is served from the cache without
+/// spending rate limit, and handles running out of rate limit.
+pub struct ApiClient {
+    http: HttpClient,
+    responses: Mutex<Cache<CachedResponse>>,
+}
+
+impl ApiClient {
+    pub fn new(cache_file: PathBuf, http: HttpClient) -> Self {
+        Self {
+            http,
+            responses: Mutex::new(Cache::new(cache_file, CACHED_RESPONSES)),
+        }
+    }
+
+    /// Sends a GET request for `url` and returns the response. A cached response for the same
+    /// URL is revalidated with `If-None-Match`/`If-Modified-Since`. When the rate limit is exceeded,
+    /// the cached response is used if there is one; otherwise pup waits for the reset if it is
+    /// close, or fails with [`Error::RateLimited`].
+    pub async fn get(&self, url: &Url, request: RequestBuilder) -> Result<ApiResponse, Error> {
+        let cached = self.get_cached(url);
+        let mut waited = false;
+
+        loop {
+            let mut attempt = request
+                .try_clone()
+                .ok_or_else(|| Error::new("Request cannot be retried"))?;
+            if let Some(cached) = &cached {
+                if let Some(etag) = &cached.etag {
+                    attempt = attempt.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &cached.last_modified {
+                    attempt = attempt.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            let response = self.http.send(attempt).await?;
+            log_rate_limit(url, response.headers());
+
+            let status = response.status();
+            if status == StatusCode::NOT_MODIFIED {
+                if let Some(cached) = cached {
+                    debug!("{} has not changed, using the cached response.", url);
+                    return Ok(cached.into());
+                }
+            }
+
+            if let Some(reset) = get_rate_limit_reset(&response) {
+                if let Some(cached) = cached {
+                    warn!(
+                        "Rate limit exceeded until {}, using cached data.",
+                        reset.format("%Y-%m-%d %H:%M:%S UTC")
+                    );
+                    return Ok(cached.into());
+                }
+
+                let wait = (reset - Utc::now()).to_std().unwrap_or_default();
+                if !waited && wait <= MAX_RATE_LIMIT_WAIT {
+                    info!(
+                        "Rate limit exceeded, waiting {} seconds for it to reset.",
+                        wait.as_secs()
+                    );
+                    tokio::time::sleep(wait).await;
+                    waited = true;
+                    continue;
+                }
+
+                return Err(Error::RateLimited { reset });
+            }
+
+            if status == StatusCode::NOT_FOUND {
+                return Err(Error::NotFound(url.to_string()));
+            }
+
+            if !status.is_success() {
+                return Err(Error::Api(format!("{} returned {}", url, status)));
+            }
+
+            let etag = get_header(response.headers(), ETAG);
+            let last_modified = get_header(response.headers(), LAST_MODIFIED);
+            let next = get_next_link(response.headers());
+            let body = response.text().await?;
+
+            if etag.is_some() || last_modified.is_some() {
+                self.responses.lock().unwrap().update(CachedResponse {
+                    url: url.to_string(),
+                    etag,
+                    last_modified,
+                    body: body.clone(),
+                    next: next.clone(),
+                    fetched_at: Utc::now(),
+                })?;
+            }
+
+            return Ok(ApiResponse { body, next });
+        }
+    }
+
+    fn get_cached(&self, url: &Url) -> Option<CachedResponse> {
+        self.responses
+            .lock()
+            .unwrap()
+            .data
+            .iter()
+            .find(|r| r.url == url.as_str())
+            .cloned()
+    }
+}
+
+fn get_header(headers: &HeaderMap, name: impl AsHeaderName) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(String::from)
+}
+
+/// Finds the `rel="next"` URL in a `Link` header such as
+/// `<https://api.github.com/...&page=2>; rel="next", <https://api.github.com/...&page=5>; rel="last"`.
+pub fn get_next_link(headers: &HeaderMap) -> Option<Url> {
+    let link = get_header(headers, LINK)?;
+    link.split(',').find_map(|part| {
+        let mut params = part.split(';');
+        let url = params.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+        params
+            .any(|p| matches!(p.trim(), "rel=\"next\"" | "rel=next"))
+            .then(|| Url::parse(url).ok())
+            .flatten()
+    })
+}
+
+fn log_rate_limit(url: &Url, headers: &HeaderMap) {
+    if let Some(remaining) = get_header(headers, "x-ratelimit-remaining") {
+        debug!("{} requests remaining after {}", remaining, url);
+    }
+}
+
+/// Returns when the rate limit resets if the response says it has been exceeded.
+fn get_rate_limit_reset(response: &Response) -> Option<DateTime<Utc>> {
+    let status = response.status();
+    if status != StatusCode::FORBIDDEN && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    let headers = response.headers();
+    let retry_after = http::retry_after(headers);
+    let exhausted =
+        get_header(headers, "x-ratelimit-remaining").is_some_and(|remaining| remaining == "0");
+
+    if let Some(retry_after) = retry_after {
+        return Some(Utc::now() + chrono::Duration::from_std(retry_after).unwrap_or_default());
+    }
+
+    if !exhausted && status != StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+
+    let reset = get_header(headers, "x-ratelimit-reset")
+        .and_then(|v| v.parse::<i64>().ok())
+        .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single());
+
+    Some(reset.unwrap_or_else(|| Utc::now() + chrono::Duration::seconds(60)))
+}
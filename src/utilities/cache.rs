@@ -13,6 +13,7 @@ where
     pub data: HashSet<T>,
     pub path: PathBuf,
     count: usize,
+    keep: fn(&T) -> bool,
 }
 
 impl<T> Cache<T>
@@ -28,13 +29,14 @@ where
             data,
             path: cache_file,
             count,
+            keep: |_| false,
         }
     }
 
-    pub fn add(&mut self, new_data: T) {
-        self.data.insert(new_data);
-
-        self.save();
+    /// Never evicts the entries `keep` returns true for, even past the cache's `count`.
+    pub fn keep_if(mut self, keep: fn(&T) -> bool) -> Self {
+        self.keep = keep;
+        self
     }
 
     pub fn update(&mut self, new_data: T) -> Result<(), Error> {
@@ -44,20 +46,17 @@ where
         Ok(())
     }
 
+    /// Adds `new_data`, replacing the entries it is equal to.
     pub fn extend(&mut self, new_data: Vec<T>) {
-        self.data = self
-            .data
-            .union(&new_data.into_iter().collect::<HashSet<T>>())
+        self.data = new_data
+            .into_iter()
+            .collect::<HashSet<T>>()
+            .union(&self.data)
             .cloned()
             .collect();
         self.save();
     }
 
-    pub fn update_range(&mut self, new_data: Vec<T>) {
-        self.data.extend(new_data.into_iter());
-        self.save();
-    }
-
     pub fn get(&self) -> Vec<T> {
         self.sort()
     }
@@ -66,9 +65,13 @@ where
         let mut cache = File::create(&self.path).unwrap();
 
         if self.data.len() > self.count {
-            let mut data: Vec<T> = self.sort().into_iter().collect();
-            data.truncate(self.count);
-            self.data = data.into_iter().collect();
+            self.data = self
+                .sort()
+                .into_iter()
+                .enumerate()
+                .filter(|(i, item)| *i < self.count || (self.keep)(item))
+                .map(|(_, item)| item)
+                .collect();
         }
 
         serde_json::to_writer(&mut cache, &self.data).unwrap();
@@ -0,0 +1,113 @@
+use checksums::Algorithm;
+
+/// Extensions of checksum files that sit next to the file they describe, e.g.
+/// `GE-Proton9-1.sha512sum` next to `GE-Proton9-1.tar.gz`.
+const CHECKSUM_EXTENSIONS: [&str; 8] = [
+    "sha512sum",
+    "sha256sum",
+    "sha1sum",
+    "md5sum",
+    "sha512",
+    "sha256",
+    "sha1",
+    "md5",
+];
+
+/// A line of a checksum file. `filename` is `None` for files that only contain a digest.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ChecksumEntry {
+    pub filename: Option<String>,
+    pub digest: String,
+}
+
+/// Whether `name` is a checksum file for `filename` alone, named after the file or its `stem`.
+pub fn is_sibling_checksum(name: &str, filename: &str, stem: &str) -> bool {
+    [filename, stem].iter().any(|base| {
+        name.strip_prefix(base)
+            .and_then(|rest| rest.strip_prefix('.'))
+            .is_some_and(|extension| CHECKSUM_EXTENSIONS.contains(&extension))
+    })
+}
+
+/// Whether `name` looks like a checksum file, either for a single file or for a whole release,
+/// such as `SHA256SUMS` or `checksums.txt`.
+pub fn is_checksum_file(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let name = name.strip_suffix(".txt").unwrap_or(&name);
+    name.ends_with("sums")
+        || name
+            .rsplit_once('.')
+            .is_some_and(|(_, extension)| CHECKSUM_EXTENSIONS.contains(&extension))
+}
+
+/// Parses the `sha*sum` (`<digest>  <name>` or `<digest> *<name>`) and BSD
+/// (`SHA256 (<name>) = <digest>`) lines in `text`, skipping anything else.
+pub fn parse(text: &str) -> Vec<ChecksumEntry> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+fn parse_line(line: &str) -> Option<ChecksumEntry> {
+    let line = line.trim().trim_matches('`').trim();
+    let line = line.strip_prefix('\\').unwrap_or(line);
+
+    if let Some((left, digest)) = line.rsplit_once(" = ") {
+        let (_, name) = left.split_once(" (")?;
+        let name = name.strip_suffix(')')?;
+        let digest = digest.trim();
+        return is_digest(digest).then(|| ChecksumEntry {
+            filename: Some(name.to_string()),
+            digest: digest.to_string(),
+        });
+    }
+
+    let (digest, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    if !is_digest(digest) {
+        return None;
+    }
+
+    let name = name.trim_start();
+    let name = name.strip_prefix('*').unwrap_or(name).trim_end();
+    Some(ChecksumEntry {
+        filename: (!name.is_empty()).then(|| name.to_string()),
+        digest: digest.to_string(),
+    })
+}
+
+fn is_digest(digest: &str) -> bool {
+    algorithm_for(digest).is_some() && digest.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Guesses the algorithm that produced a hex digest from its length.
+pub fn algorithm_for(digest: &str) -> Option<Algorithm> {
+    match digest.len() {
+        128 => Some(Algorithm::SHA2512),
+        64 => Some(Algorithm::SHA2256),
+        40 => Some(Algorithm::SHA1),
+        32 => Some(Algorithm::MD5),
+        _ => None,
+    }
+}
+
+/// Finds the digest for `filename` in a checksum file. Entries without a filename are only
+/// trusted from a `sibling` file, which describes that one file.
+pub fn find(text: &str, filename: &str, sibling: bool) -> Option<(String, Algorithm)> {
+    let entries = parse(text);
+    let entry = entries
+        .iter()
+        .find(|e| {
+            e.filename
+                .as_deref()
+                .is_some_and(|name| names_file(name, filename))
+        })
+        .or_else(|| entries.iter().find(|e| sibling && e.filename.is_none()))?;
+
+    Some((entry.digest.clone(), algorithm_for(&entry.digest)?))
+}
+
+/// Whether a path in a checksum file, such as `./dist/GE-Proton9-1.tar.gz`, names `filename`.
+fn names_file(name: &str, filename: &str) -> bool {
+    name == filename
+        || name
+            .strip_suffix(filename)
+            .is_some_and(|directory| directory.ends_with('/'))
+}
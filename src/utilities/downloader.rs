@@ -1,18 +1,183 @@
+use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use base_url::BaseUrl;
-use checksums::{hash_file, Algorithm};
-use reqwest::get;
-use tokio::fs::File;
+use checksums::{hash_reader, Algorithm};
+use reqwest::header::{HeaderMap, CONTENT_RANGE, RANGE};
+use reqwest::{StatusCode, Url};
+use tokio::fs::{File, OpenOptions};
 use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc::UnboundedSender;
 
+use crate::config::NetworkConfig;
 use crate::error::Error;
+use crate::utilities::http;
+use crate::utilities::http::HttpClient;
+use crate::utilities::ledger;
+use crate::utilities::ledger::{AssetKey, Ledger};
+use crate::utilities::signature;
+use crate::utilities::signature::{Signature, TrustedKeys};
+
+/// The stage of an install a [`ProgressEvent`] is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressPhase {
+    Download,
+    Verify,
+    Extract,
+}
+
+/// How far along a phase is. `bytes` counts the bytes downloaded, hashed or read from the archive.
+#[derive(Debug, Clone)]
+pub struct ProgressEvent {
+    pub phase: ProgressPhase,
+    pub bytes: u64,
+    /// The total number of bytes, if known, e.g. from `Content-Length`.
+    pub total: Option<u64>,
+    /// Bytes per second since the phase started.
+    pub rate: f64,
+    pub eta: Option<Duration>,
+    /// Set on the last event of the phase.
+    pub done: bool,
+}
+
+/// Receives progress events, e.g. to draw a progress bar.
+pub type ProgressSink = Arc<dyn Fn(&ProgressEvent) + Send + Sync>;
+
+/// A sink that forwards every event to a channel, for consumers that would rather poll.
+pub fn channel_sink(sender: UnboundedSender<ProgressEvent>) -> ProgressSink {
+    Arc::new(move |event| {
+        let _ = sender.send(event.clone());
+    })
+}
+
+/// How often progress is reported while a phase is running.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Turns byte counts into progress events, at most one every [`PROGRESS_INTERVAL`].
+pub(crate) struct ProgressTracker {
+    sink: Option<ProgressSink>,
+    phase: ProgressPhase,
+    total: Option<u64>,
+    bytes: u64,
+    initial_bytes: u64,
+    started: Instant,
+    last_report: Instant,
+    finished: bool,
+}
+
+impl ProgressTracker {
+    pub fn new(sink: Option<ProgressSink>, phase: ProgressPhase, total: Option<u64>) -> Self {
+        Self::resumed(sink, phase, total, 0)
+    }
+
+    /// Starts tracking at `bytes`, for a phase that picks up where an earlier attempt stopped.
+    pub fn resumed(
+        sink: Option<ProgressSink>,
+        phase: ProgressPhase,
+        total: Option<u64>,
+        bytes: u64,
+    ) -> Self {
+        let now = Instant::now();
+        let mut tracker = Self {
+            sink,
+            phase,
+            total,
+            bytes,
+            initial_bytes: bytes,
+            started: now,
+            last_report: now,
+            finished: false,
+        };
+        tracker.report(false);
+        tracker
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.bytes += bytes;
+        if self.last_report.elapsed() >= PROGRESS_INTERVAL {
+            self.report(false);
+        }
+    }
+
+    /// Reports the last event of the phase. Only the first call has an effect.
+    pub fn finish(&mut self) {
+        if !self.finished {
+            self.finished = true;
+            self.report(true);
+        }
+    }
+
+    fn report(&mut self, done: bool) {
+        let sink = match &self.sink {
+            Some(sink) => sink,
+            None => return,
+        };
+
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let rate = match elapsed > 0.0 {
+            true => (self.bytes - self.initial_bytes) as f64 / elapsed,
+            false => 0.0,
+        };
+        let eta = self
+            .total
+            .filter(|_| rate > 0.0)
+            .map(|total| Duration::from_secs_f64(total.saturating_sub(self.bytes) as f64 / rate));
+
+        sink(&ProgressEvent {
+            phase: self.phase,
+            bytes: self.bytes,
+            total: self.total,
+            rate,
+            eta,
+            done,
+        });
+        self.last_report = Instant::now();
+    }
+}
+
+/// A reader that reports how much has been read from it.
+pub(crate) struct ProgressReader<R> {
+    inner: R,
+    tracker: ProgressTracker,
+}
+
+impl<R> ProgressReader<R> {
+    pub fn new(inner: R, tracker: ProgressTracker) -> Self {
+        Self { inner, tracker }
+    }
+
+    /// Ends the phase, for readers like decoders that may stop before the end of the input.
+    pub fn finish(mut self) -> R {
+        self.tracker.finish();
+        self.inner
+    }
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        match read {
+            0 => self.tracker.finish(),
+            read => self.tracker.advance(read as u64),
+        }
+        Ok(read)
+    }
+}
+
+impl<R: Seek> Seek for ProgressReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
 
 pub struct Downloader {
     download: Option<DownloadParams>,
     cache: Option<CacheParams>,
     verify: Option<VerifyParams>,
+    signature: Option<SignatureParams>,
 }
 
 impl Downloader {
@@ -70,7 +235,67 @@ impl Downloader {
             download,
             cache,
             verify,
+            signature: None,
+        }
+    }
+
+    /// Reports the progress of the download and verification to `sink`.
+    pub fn with_progress(mut self, sink: Option<ProgressSink>) -> Self {
+        if let Some(download) = self.download.as_mut() {
+            download.progress = sink.clone();
+        }
+        if let Some(verify) = self.verify.as_mut() {
+            verify.progress = sink;
+        }
+
+        self
+    }
+
+    /// Pins the file's hash in `ledger` once its checksum matches, see [`Ledger::check`].
+    pub fn with_ledger(mut self, ledger: Arc<Mutex<Ledger>>, key: AssetKey) -> Self {
+        if let Some(verify) = self.verify.as_mut() {
+            verify.pin = Some((ledger, key));
+        }
+
+        self
+    }
+
+    /// Checks the file against a detached signature by one of `keys` once its checksum matches.
+    /// `None` means the release has no signature, which fails the download.
+    pub fn with_signature(mut self, signature: Option<Signature>, keys: TrustedKeys) -> Self {
+        let remove_failed = self.verify.as_ref().is_some_and(|v| v.remove_failed);
+        let filename = self
+            .download
+            .as_ref()
+            .and_then(|d| d.destination.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        self.signature = Some(SignatureParams {
+            filename,
+            signature,
+            keys,
+            remove_failed,
+        });
+
+        self
+    }
+
+    /// Downloads through `client` rather than a client with the default network settings.
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        if let Some(download) = self.download.as_mut() {
+            download.client = client;
+        }
+
+        self
+    }
+
+    /// Sends `headers` with the download request, e.g. to authenticate.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        if let Some(download) = self.download.as_mut() {
+            download.headers = headers;
         }
+
+        self
     }
 
     async fn try_get_from_cache(&self) -> Option<&PathBuf> {
@@ -80,9 +305,7 @@ impl Downloader {
                 &self.cache.as_ref().unwrap().cache_path
             );
 
-            let path = cache.get_file().await;
-            if path.is_ok() {
-                let path = path.unwrap();
+            if let Ok(path) = cache.get_file().await {
                 debug!("Found file in cache: {:?}", path);
                 return Some(path);
             } else {
@@ -114,53 +337,195 @@ pub trait FileGetter {
 impl FileGetter for Downloader {
     async fn get_file(&self) -> Result<&PathBuf, Error> {
         let mut path = self.try_get_from_cache().await;
-        if path.is_some() {
+        let cached = path.is_some();
+        if cached {
             info!("Found file in cache, skipping download.");
         } else {
             path = self.try_download().await?;
         }
 
-        if path.is_none() {
-            return Err(Error::NotFound(String::from(
-                path.unwrap().to_str().unwrap(),
-            )));
-        }
-
-        let path = path.unwrap();
+        let path = path.ok_or(Error::NoDownloadStrategy)?;
         if let Some(verify) = &self.verify {
             verify.verify(path).await?;
         } else {
             warn!("No checksum provided, skipping verification.");
         }
 
-        Ok(path)
+        if let Some(signature) = &self.signature {
+            signature.verify(path).await?;
+        } else {
+            debug!("No trusted keys configured, skipping signature verification.");
+        }
+
+        match (cached, &self.download) {
+            (false, Some(download)) => download.complete().await,
+            _ => Ok(path),
+        }
     }
 }
 
+/// Downloads to `<destination>.part`, which is only moved to `destination` by
+/// [`DownloadParams::complete`] once the file has been verified.
 struct DownloadParams {
     url: BaseUrl,
     destination: PathBuf,
+    part: PathBuf,
+    headers: HeaderMap,
+    progress: Option<ProgressSink>,
+    client: HttpClient,
 }
 
 impl DownloadParams {
     fn new(url: BaseUrl, destination: PathBuf) -> Result<Self, Error> {
-        Ok(Self { url, destination })
+        let mut part = destination.clone().into_os_string();
+        part.push(".part");
+
+        Ok(Self {
+            url,
+            destination,
+            part: part.into(),
+            headers: HeaderMap::new(),
+            progress: None,
+            client: HttpClient::new(&NetworkConfig::default())?,
+        })
+    }
+
+    /// Moves the verified download into place.
+    async fn complete(&self) -> Result<&PathBuf, Error> {
+        debug!("Moving {:?} to {:?}", self.part, self.destination);
+        tokio::fs::rename(&self.part, &self.destination).await?;
+        Ok(&self.destination)
+    }
+
+    /// Makes one attempt at downloading the rest of the file to its `.part` path.
+    async fn try_download(&self) -> Result<(), Error> {
+        let mut resume_from = tokio::fs::metadata(&self.part)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0);
+
+        let response = loop {
+            let mut request = self
+                .client
+                .get(self.url.as_str())
+                .headers(self.headers.clone());
+            if resume_from > 0 {
+                request = request.header(RANGE, format!("bytes={}-", resume_from));
+            }
+
+            let response = self.client.within(request.send()).await?;
+            if let Some(error) = http::status_error(&response) {
+                return Err(error);
+            }
+            if response.status() != StatusCode::RANGE_NOT_SATISFIABLE || resume_from == 0 {
+                break response.error_for_status()?;
+            }
+
+            // The partial file is at least as long as the file on the server.
+            if get_content_length(response.headers()) == Some(resume_from) {
+                debug!("{:?} is already complete", self.part);
+                return Ok(());
+            }
+
+            debug!("Cannot resume {:?}, starting over", self.part);
+            resume_from = 0;
+        };
+
+        let mut response = response;
+        let resumed = response.status() == StatusCode::PARTIAL_CONTENT;
+        let mut file = if resumed {
+            info!("Resuming download at {} bytes.", resume_from);
+            OpenOptions::new().append(true).open(&self.part).await?
+        } else {
+            resume_from = 0;
+            File::create(&self.part).await?
+        };
+
+        let total = response.content_length().map(|length| length + resume_from);
+        let mut progress = ProgressTracker::resumed(
+            self.progress.clone(),
+            ProgressPhase::Download,
+            total,
+            resume_from,
+        );
+        while let Some(chunk) = self.client.within(response.chunk()).await? {
+            file.write_all(&chunk).await?;
+            progress.advance(chunk.len() as u64);
+        }
+        file.flush().await?;
+        progress.finish();
+        Ok(())
     }
 }
 
 #[async_trait]
 impl FileGetter for DownloadParams {
+    /// Downloads the file to its `.part` path, resuming a previous partial download if the
+    /// server supports range requests. Transient failures are retried.
     async fn get_file(&self) -> Result<&PathBuf, Error> {
-        let mut file = File::create(self.destination.clone()).await?;
-        let mut response = get(self.url.as_str()).await?;
-        while let Some(chunk) = response.chunk().await? {
-            file.write_all(&chunk).await?;
+        if self.url.scheme() == "file" {
+            let source = file_url_to_path(self.url.as_str())?;
+            debug!("Copying {} to {:?}", source.display(), self.part);
+            let total = tokio::fs::metadata(&source).await?.len();
+            let mut progress =
+                ProgressTracker::new(self.progress.clone(), ProgressPhase::Download, Some(total));
+            tokio::fs::copy(&source, &self.part).await?;
+            progress.advance(total);
+            progress.finish();
+            return Ok(&self.part);
         }
-        file.flush().await?;
-        Ok(&self.destination)
+
+        // Interrupted attempts leave the `.part` file behind, so retries resume where they
+        // stopped.
+        self.client.retry(move |_| self.try_download()).await?;
+        Ok(&self.part)
     }
 }
 
+/// Reads the full length of a file from a `Content-Range: bytes */<length>` header.
+fn get_content_length(headers: &HeaderMap) -> Option<u64> {
+    headers
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .rsplit_once('/')?
+        .1
+        .parse()
+        .ok()
+}
+
+/// Reads a small text file, such as a checksum file, from an HTTP(S) or `file://` URL.
+pub async fn get_text(client: &HttpClient, url: &Url, headers: HeaderMap) -> Result<String, Error> {
+    let bytes = get_bytes(client, url, headers).await?;
+    String::from_utf8(bytes).map_err(|_| Error::new(&format!("{} is not a text file", url)))
+}
+
+/// Reads a small file, such as a signature, from an HTTP(S) or `file://` URL.
+pub async fn get_bytes(
+    client: &HttpClient,
+    url: &Url,
+    headers: HeaderMap,
+) -> Result<Vec<u8>, Error> {
+    if url.scheme() == "file" {
+        let path = file_url_to_path(url.as_str())?;
+        return Ok(tokio::fs::read(path).await?);
+    }
+
+    let response = client
+        .send(client.get(url.clone()).headers(headers))
+        .await?
+        .error_for_status()?;
+
+    Ok(response.bytes().await?.to_vec())
+}
+
+fn file_url_to_path(url: &str) -> Result<PathBuf, Error> {
+    Url::parse(url)
+        .ok()
+        .and_then(|url| url.to_file_path().ok())
+        .ok_or_else(|| Error::Url(url.to_string()))
+}
+
 struct CacheParams {
     cache_path: PathBuf,
 }
@@ -189,10 +554,33 @@ impl FileGetter for CacheParams {
     }
 }
 
+/// Hashes the file at `path` on a blocking thread, reporting progress to `sink` as the verify
+/// phase.
+async fn hash(
+    path: &Path,
+    algorithm: Algorithm,
+    sink: Option<ProgressSink>,
+) -> Result<String, Error> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(path)?;
+        let size = file.metadata()?.len();
+        let tracker = ProgressTracker::new(sink, ProgressPhase::Verify, Some(size));
+        let mut reader = ProgressReader::new(std::io::BufReader::new(file), tracker);
+        let hash = hash_reader(&mut reader, algorithm);
+        reader.finish();
+        Ok(hash)
+    })
+    .await
+    .map_err(|e| Error::new(&format!("Hashing stopped: {}", e)))?
+}
+
 struct VerifyParams {
     checksum: String,
     checksum_algorithm: Algorithm,
     remove_failed: bool,
+    pin: Option<(Arc<Mutex<Ledger>>, AssetKey)>,
+    progress: Option<ProgressSink>,
 }
 
 impl VerifyParams {
@@ -202,6 +590,8 @@ impl VerifyParams {
             checksum,
             checksum_algorithm,
             remove_failed,
+            pin: None,
+            progress: None,
         }
     }
 
@@ -209,7 +599,7 @@ impl VerifyParams {
         info!("Verifying file...");
         debug!("Checksum: {}", self.checksum);
 
-        let checksum = hash_file(file_path, self.checksum_algorithm);
+        let checksum = hash(file_path, self.checksum_algorithm, self.progress.clone()).await?;
         if !checksum.eq_ignore_ascii_case(&self.checksum) {
             debug!("Failed to verify file: {}", file_path.display());
             if self.remove_failed {
@@ -223,6 +613,60 @@ impl VerifyParams {
         }
 
         info!("Checksums match.");
+
+        if let Some((ledger, key)) = &self.pin {
+            let hash = match self.checksum_algorithm == ledger::PIN_ALGORITHM {
+                true => checksum,
+                false => hash(file_path, ledger::PIN_ALGORITHM, None).await?,
+            };
+            let pinned = ledger.lock().unwrap().check(key, &hash);
+            if let Err(e) = pinned {
+                if self.remove_failed {
+                    debug!("Removing file: {}", file_path.display());
+                    tokio::fs::remove_file(file_path).await?;
+                }
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct SignatureParams {
+    filename: String,
+    signature: Option<Signature>,
+    keys: TrustedKeys,
+    remove_failed: bool,
+}
+
+impl SignatureParams {
+    pub async fn verify(&self, file_path: &Path) -> Result<(), Error> {
+        let signature = self
+            .signature
+            .as_ref()
+            .ok_or_else(|| Error::SignatureMissing(self.filename.clone()))?;
+
+        info!("Verifying signature {}...", signature.name);
+        let (path, signature, keys) = (
+            file_path.to_path_buf(),
+            signature.clone(),
+            self.keys.clone(),
+        );
+        let result =
+            tokio::task::spawn_blocking(move || signature::verify(&path, &signature, &keys))
+                .await
+                .map_err(|e| Error::new(&format!("Signature verification stopped: {}", e)))?;
+        if let Err(e) = result {
+            debug!("Failed to verify signature of {}", file_path.display());
+            if self.remove_failed && matches!(e, Error::SignatureInvalid(_)) {
+                debug!("Removing file: {}", file_path.display());
+                tokio::fs::remove_file(file_path).await?;
+            }
+            return Err(e);
+        }
+
+        info!("Signature is valid.");
         Ok(())
     }
 }
@@ -1,113 +1,785 @@
+use std::collections::HashSet;
+use std::ffi::OsString;
 use std::fs::File;
-use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
+use std::fs::Permissions;
+use std::io::{BufWriter, Cursor, Read, Seek, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Component, Path, PathBuf};
+use std::time::Instant;
 
+use bzip2::read::BzDecoder;
 use flate2::read::GzDecoder;
-use tar::Archive;
-use tokio::io::AsyncBufReadExt;
-use xz2::read::XzDecoder;
+use liblzma::read::XzDecoder;
+use liblzma::stream::MtStreamBuilder;
+use tar::{Archive, Entry, EntryType};
+use zip::ZipArchive;
 
+use crate::config::ConfigModule;
 use crate::error::Error;
+use crate::utilities::downloader::{ProgressPhase, ProgressReader, ProgressSink, ProgressTracker};
+use crate::utilities::file_writer::FileWriter;
 
-const SUPPORTED_EXTENSIONS: [&str; 2] = ["gz", "xz"];
+const DEFAULT_MAX_EXTRACT_SIZE: u64 = 16 * 1024 * 1024 * 1024;
+const DEFAULT_MAX_EXTRACT_FILES: u64 = 500_000;
+
+const SUPPORTED_EXTENSIONS: [&str; 5] = ["gz", "xz", "zst", "bz2", "zip"];
+const ARCHIVE_SUFFIXES: [&str; 5] = [".tar.gz", ".tar.xz", ".tar.zst", ".tar.bz2", ".zip"];
+
+/// The size of a tar header, which holds `ustar` at [`TAR_MAGIC_OFFSET`] in any modern tarball.
+const TAR_HEADER_SIZE: u64 = 512;
+const TAR_MAGIC_OFFSET: usize = 257;
+
+/// Files up to this size are read into memory and written on another thread. Larger files are
+/// streamed straight to disk.
+const MAX_BUFFERED_FILE: u64 = 4 * 1024 * 1024;
+
+/// How much memory the xz decoder may use to decompress blocks in parallel. Beyond this, it uses
+/// fewer threads.
+const XZ_THREADING_MEMORY: u64 = 1024 * 1024 * 1024;
+
+/// How many links a symlink target may go through, as on Linux.
+const MAX_LINKS: u32 = 40;
+
+/// The file type bits of a Unix mode, and their value for symlinks.
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// How an archive is compressed or packed, told apart by the bytes it starts with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Gzip,
+    Xz,
+    Zstd,
+    Bzip2,
+    Zip,
+    Uncompressed,
+}
+
+const SIGNATURES: [(&[u8], Format); 6] = [
+    (&[0x1f, 0x8b], Format::Gzip),
+    (&[0xfd, b'7', b'z', b'X', b'Z', 0x00], Format::Xz),
+    (&[0x28, 0xb5, 0x2f, 0xfd], Format::Zstd),
+    (b"BZh", Format::Bzip2),
+    (b"PK\x03\x04", Format::Zip),
+    // An empty zip file.
+    (b"PK\x05\x06", Format::Zip),
+];
 
 pub fn is_supported_extension(extension: &str) -> bool {
     SUPPORTED_EXTENSIONS.contains(&extension)
 }
 
-/// Extracts an archived/compressed file, returning a list of files extracted.
-pub fn extract(archive: &Path, destination: &Path) -> Result<Vec<String>, Error> {
-    let extension = archive.extension().unwrap().to_str().unwrap();
+/// Limits on what an archive may extract to, so that an archive bomb fails instead of filling
+/// the disk.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractLimits {
+    /// The total size of the extracted files, in bytes.
+    pub max_size: u64,
+    /// The number of entries, including directories and links.
+    pub max_files: u64,
+}
 
-    if !(is_supported_extension(extension)) {
-        return Err(Error::FileTypeNotSupported(
-            archive.extension().unwrap().to_str().unwrap().to_string(),
-        ));
+impl Default for ExtractLimits {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_MAX_EXTRACT_SIZE,
+            max_files: DEFAULT_MAX_EXTRACT_FILES,
+        }
     }
+}
+
+impl ExtractLimits {
+    pub fn from_config(config: &ConfigModule) -> Self {
+        let defaults = Self::default();
+        Self {
+            max_size: config.max_extract_size.unwrap_or(defaults.max_size),
+            max_files: config.max_extract_files.unwrap_or(defaults.max_files),
+        }
+    }
+}
+
+/// How to extract an archive.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractOptions {
+    pub limits: ExtractLimits,
+    /// Threads used to decompress xz archives and to write files.
+    pub threads: usize,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            limits: ExtractLimits::default(),
+            threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+}
+
+impl ExtractOptions {
+    pub fn from_config(config: &ConfigModule) -> Self {
+        let defaults = Self::default();
+        Self {
+            limits: ExtractLimits::from_config(config),
+            threads: config
+                .extract_threads
+                .filter(|threads| *threads > 0)
+                .unwrap_or(defaults.threads),
+        }
+    }
+}
+
+/// Returns the name of an archive without its suffix, or `None` if it is not a supported archive.
+pub fn archive_stem(name: &str) -> Option<&str> {
+    ARCHIVE_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+}
+
+/// Extracts an archived/compressed file, returning a list of files extracted. The format is
+/// detected from the contents of the file rather than its name.
+pub fn extract(
+    archive: &Path,
+    destination: &Path,
+    options: &ExtractOptions,
+    progress: Option<ProgressSink>,
+) -> Result<Vec<String>, Error> {
+    let format = detect_format(archive)?;
+    let size = std::fs::metadata(archive)?.len();
+    let started = Instant::now();
+    debug!(
+        "Started extracting {} ({} bytes) as {:?} with {} threads at {}",
+        archive.display(),
+        size,
+        format,
+        options.threads,
+        chrono::Local::now().format("%H:%M:%S%.3f")
+    );
 
     if !destination.exists() {
         std::fs::create_dir_all(destination)?;
     }
 
-    match extension {
-        "gz" => extract_gz(archive, destination),
-        "xz" => extract_xz(archive, destination),
-        _ => Err(Error::FileTypeNotSupported(extension.to_string())),
-    }
+    let mut reader = open_archive(archive, progress)?;
+    let contents = match format {
+        Format::Zip => unpack_zip(&mut reader, destination, options),
+        format => decompress(&mut reader, format, options.threads)
+            .and_then(|decoder| unpack(decoder, archive, format, destination, options)),
+    };
+    reader.finish();
+
+    let elapsed = started.elapsed().as_secs_f64();
+    debug!(
+        "Finished extracting {} at {} after {:.2} seconds ({:.1} MiB/s of archive)",
+        archive.display(),
+        chrono::Local::now().format("%H:%M:%S%.3f"),
+        elapsed,
+        size as f64 / (1024.0 * 1024.0) / elapsed.max(f64::EPSILON)
+    );
+    contents
+}
+
+fn detect_format(archive: &Path) -> Result<Format, Error> {
+    let mut magic = Vec::new();
+    File::open(archive)?.take(6).read_to_end(&mut magic)?;
+
+    Ok(SIGNATURES
+        .iter()
+        .find(|(signature, _)| magic.starts_with(signature))
+        .map_or(Format::Uncompressed, |(_, format)| *format))
+}
+
+/// Wraps `reader` in a decoder for `format`. xz archives made of several blocks, as multi-threaded
+/// compressors write them, are decompressed on up to `threads` threads.
+///
+/// zstd is decoded on a single thread: libzstd only has a multi-threaded compressor, and each
+/// block of a zstd frame refers back to data decoded from the blocks before it, so they cannot be
+/// handed to several decoders. gzip streams are sequential in the same way, and the bzip2 crate
+/// has no parallel decoder. For these formats only the file writes are parallel.
+fn decompress<'a>(
+    reader: &'a mut impl Read,
+    format: Format,
+    threads: usize,
+) -> Result<Box<dyn Read + 'a>, Error> {
+    Ok(match format {
+        Format::Gzip => Box::new(GzDecoder::new(reader)),
+        Format::Xz => {
+            let stream = MtStreamBuilder::new()
+                .threads(threads.try_into().unwrap_or(u32::MAX))
+                .memlimit_threading(XZ_THREADING_MEMORY)
+                .memlimit_stop(u64::MAX)
+                .decoder()
+                .map_err(|e| Error::new(&format!("Cannot decompress xz: {}", e)))?;
+            Box::new(XzDecoder::new_stream(reader, stream))
+        }
+        Format::Zstd => Box::new(zstd::Decoder::new(reader)?),
+        Format::Bzip2 => Box::new(BzDecoder::new(reader)),
+        Format::Zip | Format::Uncompressed => Box::new(reader),
+    })
 }
 
-fn unpack_tar_and_save(buffer: Vec<u8>, destination: &Path) -> Result<Vec<String>, Error> {
-    let mut archive = Archive::new(buffer.as_slice());
+/// Runs `unpack_entries` on the canonical `destination`, returning the top-level contents it
+/// collects. Unsafe or failing entries are collected too, and if there are any, everything the
+/// archive added to `destination` is removed again.
+fn unpack_checked(
+    destination: &Path,
+    unpack_entries: impl FnOnce(&Path, &mut Vec<String>, &mut Vec<String>) -> Result<(), Error>,
+) -> Result<Vec<String>, Error> {
+    let root = destination.canonicalize()?;
+    let existing = std::fs::read_dir(&root)?
+        .map(|entry| entry.map(|e| e.file_name()))
+        .collect::<Result<HashSet<_>, _>>()?;
+
     let mut contents = Vec::new();
+    let mut failures = Vec::new();
+    let result = match unpack_entries(&root, &mut contents, &mut failures) {
+        Ok(()) if failures.is_empty() => Ok(contents),
+        Ok(()) => Err(Error::ExtractFailed(failures)),
+        Err(e) => Err(e),
+    };
 
-    archive
-        .entries()?
-        .filter_map(|e| e.ok())
-        .map(|mut entry| -> Result<PathBuf, Error> {
-            let path = entry
-                .path()?
-                .components()
-                .fold(destination.to_path_buf(), |mut path, c| {
-                    path.push(c.as_os_str());
-                    path
-                });
-            entry.unpack(&path)?;
-            Ok(path)
+    if result.is_err() {
+        remove_new_entries(&root, &existing);
+    }
+    result
+}
+
+/// Counts the entries of an archive against its [`ExtractLimits`].
+struct EntryCounter<'a> {
+    limits: &'a ExtractLimits,
+    files: u64,
+    size: u64,
+}
+
+impl<'a> EntryCounter<'a> {
+    fn new(limits: &'a ExtractLimits) -> Self {
+        Self {
+            limits,
+            files: 0,
+            size: 0,
+        }
+    }
+
+    fn add(&mut self, size: u64) -> Result<(), Error> {
+        self.files += 1;
+        self.size += size;
+        if self.files > self.limits.max_files {
+            return Err(Error::ArchiveTooLarge(format!(
+                "more than {} files",
+                self.limits.max_files
+            )));
+        }
+        if self.size > self.limits.max_size {
+            return Err(Error::ArchiveTooLarge(format!(
+                "more than {} bytes",
+                self.limits.max_size
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+fn add_top_level(contents: &mut Vec<String>, relative: &Path) {
+    if let Some(top_level) = relative.components().next() {
+        let top_level = top_level.as_os_str().to_string_lossy().to_string();
+        if !contents.contains(&top_level) {
+            contents.push(top_level);
+        }
+    }
+}
+
+/// Unpacks a tar archive into `destination`, returning its top-level contents. Every entry is
+/// checked first: paths must stay inside `destination`, and so must the targets of links.
+fn unpack_tar(
+    reader: impl Read,
+    destination: &Path,
+    options: &ExtractOptions,
+) -> Result<Vec<String>, Error> {
+    unpack_checked(destination, |root, contents, failures| {
+        std::thread::scope(|scope| {
+            let mut writer = FileWriter::new(scope, options.threads);
+            let result = unpack_tar_entries(
+                &mut Archive::new(reader),
+                root,
+                &options.limits,
+                &mut writer,
+                contents,
+                failures,
+            );
+            failures.extend(writer.finish());
+            result
         })
-        .filter_map(|e| e.ok())
-        .for_each(|path| {
-            contents.push(
-                path.strip_prefix(destination)
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string(),
-            )
+    })
+}
+
+fn unpack_tar_entries(
+    archive: &mut Archive<impl Read>,
+    root: &Path,
+    limits: &ExtractLimits,
+    writer: &mut FileWriter,
+    contents: &mut Vec<String>,
+    failures: &mut Vec<String>,
+) -> Result<(), Error> {
+    let mut counter = EntryCounter::new(limits);
+    for entry in archive.entries()? {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                // The rest of the archive cannot be read either.
+                failures.push(format!("the archive ({})", e));
+                return Ok(());
+            }
+        };
+        let path = match entry.path() {
+            Ok(path) => path.into_owned(),
+            Err(e) => {
+                failures.push(format!("an entry ({})", e));
+                continue;
+            }
+        };
+
+        counter.add(entry.size())?;
+        let entry_type = entry.header().entry_type();
+        if entry_type.is_hard_link() || entry_type.is_symlink() {
+            // Links are checked against what is on disk, including the files being written.
+            writer.wait();
+        }
+
+        let checked = safe_relative_path(&path).and_then(|relative| {
+            check_link(&entry, root, &relative)?;
+            Ok(relative)
         });
+        let relative = match checked {
+            Ok(relative) => relative,
+            Err(reason) => {
+                failures.push(format!("{} ({})", path.display(), reason));
+                continue;
+            }
+        };
+
+        let mode = entry.header().mode().ok();
+        if entry_type == EntryType::Regular
+            && entry.size() <= MAX_BUFFERED_FILE
+            && writer.is_parallel()
+            && !writer.is_pending(&root.join(&relative))
+        {
+            let mut data = Vec::new();
+            let target =
+                replace(root, &relative).and_then(|target| match entry.read_to_end(&mut data) {
+                    Ok(_) => Ok(target),
+                    Err(e) => Err(e.to_string()),
+                });
+            match target {
+                Ok(target) => {
+                    let name = path.display().to_string();
+                    let mtime = entry.header().mtime().ok();
+                    writer.write(target, name, data, mode, mtime);
+                }
+                Err(reason) => {
+                    failures.push(format!("{} ({})", path.display(), reason));
+                    continue;
+                }
+            }
+
+            add_top_level(contents, &relative);
+            continue;
+        }
+
+        // Creating a writable directory does not depend on the files being written, anything
+        // else might.
+        let writable_dir = entry_type == EntryType::Directory
+            && mode.is_some_and(|mode| mode & 0o200 != 0)
+            && !writer.is_pending(&root.join(&relative));
+        if !writable_dir {
+            writer.wait();
+        }
+
+        match entry.unpack_in(root) {
+            Ok(true) => {}
+            Ok(false) => {
+                failures.push(format!("{} (outside the destination)", path.display()));
+                continue;
+            }
+            Err(e) => {
+                failures.push(format!("{} ({})", path.display(), e));
+                continue;
+            }
+        }
 
-    contents.retain(|c| c.split('/').count() == 1);
-    Ok(contents)
+        add_top_level(contents, &relative);
+    }
+
+    Ok(())
 }
 
-fn save(buffer: Vec<u8>, destination: &Path) -> Result<String, Error> {
-    let mut file = File::create(destination)?;
-    file.write_all(&buffer)?;
-    Ok(destination
-        .components()
-        .last()
-        .unwrap()
-        .as_os_str()
-        .to_str()
-        .unwrap()
-        .to_string())
+/// Returns `path` without `.` components, or why it is unsafe to extract.
+fn safe_relative_path(path: &Path) -> Result<PathBuf, String> {
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => relative.push(name),
+            Component::CurDir => {}
+            Component::ParentDir => return Err("contains `..`".to_string()),
+            Component::RootDir | Component::Prefix(_) => {
+                return Err("is an absolute path".to_string())
+            }
+        }
+    }
+
+    Ok(relative)
 }
 
-fn extract_gz(archive: &Path, destination: &Path) -> Result<Vec<String>, Error> {
-    let file = File::open(archive)?;
-    let mut decoder = GzDecoder::new(file);
-    let mut buffer = Vec::new();
-    decoder.read_to_end(&mut buffer)?;
+/// Checks that a link entry points inside `root`. Symlink targets are resolved from the directory
+/// the link is extracted to, following any links extracted before it.
+fn check_link<R: Read>(entry: &Entry<R>, root: &Path, relative: &Path) -> Result<(), String> {
+    let target = match entry.link_name() {
+        Ok(Some(target)) => target.into_owned(),
+        Ok(None) => return Ok(()),
+        Err(e) => return Err(e.to_string()),
+    };
 
-    let url = archive.as_os_str().to_str().unwrap();
+    match entry.header().entry_type() {
+        EntryType::Symlink => check_symlink(&target, root, relative),
+        EntryType::Link => {
+            let outside = || format!("links to {} outside the destination", target.display());
+            let target = safe_relative_path(&target).map_err(|_| outside())?;
+            let resolved = root
+                .join(target)
+                .canonicalize()
+                .map_err(|e| e.to_string())?;
+            match resolved.starts_with(root) {
+                true => Ok(()),
+                false => Err(outside()),
+            }
+        }
+        _ => Ok(()),
+    }
+}
 
-    if url.ends_with(".tar.gz") {
-        unpack_tar_and_save(buffer, destination)
-    } else {
-        Ok(vec![save(buffer, destination).unwrap()])
+/// Checks that a symlink at `relative` to `target` points inside `root`.
+fn check_symlink(target: &Path, root: &Path, relative: &Path) -> Result<(), String> {
+    let outside = || format!("links to {} outside the destination", target.display());
+    let parent = safe_parent(root, relative)?;
+    match resolve_link(root, parent, target, &mut 0) {
+        Some(_) => Ok(()),
+        None => Err(outside()),
     }
 }
 
-fn extract_xz(archive: &Path, destination: &Path) -> Result<Vec<String>, Error> {
-    let file = File::open(archive)?;
-    let mut decoder = XzDecoder::new(file);
-    let mut buffer = Vec::new();
-    decoder.read_to_end(&mut buffer)?;
+/// Resolves `target` from `dir` one component at a time against what is already extracted,
+/// following links the way the kernel would. Returns `None` if any step leaves `root`, or if a
+/// `..` follows a component that does not exist yet, since a later entry could make it a link.
+fn resolve_link(root: &Path, dir: PathBuf, target: &Path, links: &mut u32) -> Option<PathBuf> {
+    let mut resolved = dir;
+    let mut exists = true;
+    for component in target.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir if !exists => return None,
+            Component::ParentDir => {
+                resolved.pop();
+                if !resolved.starts_with(root) {
+                    return None;
+                }
+            }
+            Component::Normal(name) => {
+                resolved.push(name);
+                let metadata = match resolved.symlink_metadata() {
+                    Ok(metadata) => metadata,
+                    Err(_) => {
+                        exists = false;
+                        continue;
+                    }
+                };
+                if !metadata.file_type().is_symlink() {
+                    continue;
+                }
 
-    let url = archive.as_os_str().to_str().unwrap();
+                *links += 1;
+                if *links > MAX_LINKS {
+                    return None;
+                }
+                let link = std::fs::read_link(&resolved).ok()?;
+                resolved.pop();
+                resolved = resolve_link(root, resolved, &link, links)?;
+                exists = resolved.exists();
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(resolved)
+}
 
-    if url.ends_with(".tar.xz") {
-        unpack_tar_and_save(buffer, destination)
-    } else {
-        Ok(vec![save(buffer, destination).unwrap()])
+/// Creates the directory an entry at `relative` goes in, returning its canonical path, or why it
+/// is unsafe to extract to.
+fn safe_parent(root: &Path, relative: &Path) -> Result<PathBuf, String> {
+    safe_dir(root, relative.parent().unwrap_or(Path::new("")))
+}
+
+/// Creates the directory at `relative` one component at a time, so that nothing is created
+/// through a link that points outside `root`, and returns its canonical path.
+fn safe_dir(root: &Path, relative: &Path) -> Result<PathBuf, String> {
+    let mut dir = root.to_path_buf();
+    for component in relative.components() {
+        dir.push(component);
+        if dir.symlink_metadata().is_err() {
+            std::fs::create_dir(&dir).map_err(|e| e.to_string())?;
+        }
+
+        dir = dir.canonicalize().map_err(|e| e.to_string())?;
+        if !dir.starts_with(root) {
+            return Err("is inside a link that points outside the destination".to_string());
+        }
     }
+
+    Ok(dir)
+}
+
+/// Removes whatever was added to `root` since it contained `existing`.
+fn remove_new_entries(root: &Path, existing: &HashSet<OsString>) {
+    let entries = match std::fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("Could not clean up {}: {}", root.display(), e);
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        if existing.contains(&entry.file_name()) {
+            continue;
+        }
+
+        let path = entry.path();
+        debug!("Removing {}", path.display());
+        let removed = match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => std::fs::remove_dir_all(&path),
+            _ => std::fs::remove_file(&path),
+        };
+        if let Err(e) = removed {
+            warn!("Could not remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Writes the single file a compressed file holds to `destination`, named after the archive
+/// without its compression suffix.
+fn save(
+    reader: impl Read,
+    archive: &Path,
+    destination: &Path,
+    limits: &ExtractLimits,
+) -> Result<String, Error> {
+    let extension = archive.extension().unwrap_or_default().to_string_lossy();
+    let name = match is_supported_extension(&extension) {
+        true => archive.file_stem(),
+        false => archive.file_name(),
+    }
+    .ok_or_else(|| Error::FileTypeNotSupported(archive.display().to_string()))?
+    .to_string_lossy()
+    .to_string();
+    let path = destination.join(&name);
+
+    let mut file = BufWriter::new(File::create(&path)?);
+    let written = std::io::copy(&mut reader.take(limits.max_size + 1), &mut file)?;
+    file.flush()?;
+    if written > limits.max_size {
+        std::fs::remove_file(&path)?;
+        return Err(Error::ArchiveTooLarge(format!(
+            "more than {} bytes",
+            limits.max_size
+        )));
+    }
+
+    Ok(name)
+}
+
+/// Unpacks a decompressed stream: the entries of a tarball, or else the single file it holds.
+/// Nothing is buffered beyond what the decoder and `tar` need.
+fn unpack(
+    mut reader: impl Read,
+    archive: &Path,
+    format: Format,
+    destination: &Path,
+    options: &ExtractOptions,
+) -> Result<Vec<String>, Error> {
+    let mut header = Vec::new();
+    (&mut reader)
+        .take(TAR_HEADER_SIZE)
+        .read_to_end(&mut header)?;
+    // Old tarballs without the magic are still recognized by their name.
+    let name = archive.file_name().unwrap_or_default().to_string_lossy();
+    let is_tar = header.get(TAR_MAGIC_OFFSET..TAR_MAGIC_OFFSET + 5) == Some(b"ustar")
+        || name.contains(".tar.");
+    let reader = Cursor::new(header).chain(reader);
+
+    match (is_tar, format) {
+        (true, _) => unpack_tar(reader, destination, options),
+        (false, Format::Uncompressed) => Err(Error::FileTypeNotSupported(name.to_string())),
+        (false, _) => Ok(vec![save(reader, archive, destination, &options.limits)?]),
+    }
+}
+
+/// Unpacks a zip file into `destination`, returning its top-level contents. Entries are checked
+/// like those of a tarball, and keep their Unix permissions and symlinks.
+fn unpack_zip(
+    reader: impl Read + Seek,
+    destination: &Path,
+    options: &ExtractOptions,
+) -> Result<Vec<String>, Error> {
+    unpack_checked(destination, |root, contents, failures| {
+        std::thread::scope(|scope| {
+            let mut writer = FileWriter::new(scope, options.threads);
+            let result = unpack_zip_entries(
+                reader,
+                root,
+                &options.limits,
+                &mut writer,
+                contents,
+                failures,
+            );
+            failures.extend(writer.finish());
+            result
+        })
+    })
+}
+
+fn unpack_zip_entries(
+    reader: impl Read + Seek,
+    root: &Path,
+    limits: &ExtractLimits,
+    writer: &mut FileWriter,
+    contents: &mut Vec<String>,
+    failures: &mut Vec<String>,
+) -> Result<(), Error> {
+    let mut archive = match ZipArchive::new(reader) {
+        Ok(archive) => archive,
+        Err(e) => {
+            failures.push(format!("the archive ({})", e));
+            return Ok(());
+        }
+    };
+
+    let mut counter = EntryCounter::new(limits);
+    for index in 0..archive.len() {
+        let mut file = match archive.by_index(index) {
+            Ok(file) => file,
+            Err(e) => {
+                failures.push(format!("entry {} ({})", index, e));
+                continue;
+            }
+        };
+        let path = PathBuf::from(file.name());
+
+        counter.add(file.size())?;
+        let relative = match safe_relative_path(&path) {
+            Ok(relative) if relative.as_os_str().is_empty() => continue,
+            Ok(relative) => relative,
+            Err(reason) => {
+                failures.push(format!("{} ({})", path.display(), reason));
+                continue;
+            }
+        };
+
+        let mode = file.unix_mode();
+        let unpacked = if file.is_dir() {
+            safe_dir(root, &relative).map(|_| ())
+        } else if mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+            writer.wait();
+            let mut target = String::new();
+            file.read_to_string(&mut target)
+                .map_err(|e| e.to_string())
+                .and_then(|_| check_symlink(Path::new(&target), root, &relative))
+                .and_then(|_| {
+                    let path = replace(root, &relative)?;
+                    std::os::unix::fs::symlink(&target, path).map_err(|e| e.to_string())
+                })
+        } else if file.size() <= MAX_BUFFERED_FILE
+            && writer.is_parallel()
+            && !writer.is_pending(&root.join(&relative))
+        {
+            replace(root, &relative).and_then(|path| {
+                let data = read_file(&mut file).map_err(|e| e.to_string())?;
+                writer.write(path, file.name().to_string(), data, mode, None);
+                Ok(())
+            })
+        } else {
+            writer.wait();
+            replace(root, &relative)
+                .and_then(|path| write_file(&mut file, &path, mode).map_err(|e| e.to_string()))
+        };
+        if let Err(reason) = unpacked {
+            failures.push(format!("{} ({})", path.display(), reason));
+            continue;
+        }
+
+        add_top_level(contents, &relative);
+    }
+
+    Ok(())
+}
+
+/// Returns where to extract an entry at `relative` to, removing any file or link already there
+/// so that it is not written through.
+fn replace(root: &Path, relative: &Path) -> Result<PathBuf, String> {
+    let name = relative.file_name().unwrap_or_default();
+    let path = safe_parent(root, relative)?.join(name);
+    match path.symlink_metadata() {
+        Ok(metadata) if !metadata.is_dir() => {
+            std::fs::remove_file(&path).map_err(|e| e.to_string())?
+        }
+        _ => {}
+    }
+
+    Ok(path)
+}
+
+/// Reads a zip entry into memory. The entry must not be larger than its header says, as that is
+/// what the extraction limits were checked against.
+fn read_file(file: &mut zip::read::ZipFile) -> std::io::Result<Vec<u8>> {
+    let size = file.size();
+    let mut data = Vec::with_capacity(size as usize);
+    file.take(size + 1).read_to_end(&mut data)?;
+    match data.len() as u64 > size {
+        true => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "larger than its header says",
+        )),
+        false => Ok(data),
+    }
+}
+
+/// Streams a zip entry to `path`, with the same size check as [`read_file`].
+fn write_file(
+    file: &mut zip::read::ZipFile,
+    path: &Path,
+    mode: Option<u32>,
+) -> std::io::Result<()> {
+    let size = file.size();
+    let mut output = BufWriter::new(File::create(path)?);
+    let written = std::io::copy(&mut file.take(size + 1), &mut output)?;
+    output.flush()?;
+    if written > size {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "larger than its header says",
+        ));
+    }
+
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, Permissions::from_mode(mode & 0o777))?;
+    }
+    Ok(())
+}
+
+/// Opens an archive, reporting how much of it has been read to `progress`.
+fn open_archive(
+    archive: &Path,
+    progress: Option<ProgressSink>,
+) -> Result<ProgressReader<File>, Error> {
+    let file = File::open(archive)?;
+    let total = file.metadata()?.len();
+    let tracker = ProgressTracker::new(progress, ProgressPhase::Extract, Some(total));
+    Ok(ProgressReader::new(file, tracker))
 }
@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+use std::fs::{File, Permissions};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, SyncSender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::Scope;
+use std::time::{Duration, SystemTime};
+
+/// A file read from an archive, waiting to be written.
+struct FileJob {
+    path: PathBuf,
+    /// The path in the archive, for error messages.
+    name: String,
+    data: Vec<u8>,
+    mode: Option<u32>,
+    mtime: Option<u64>,
+}
+
+impl FileJob {
+    fn write(&self) -> std::io::Result<()> {
+        let mut file = File::create(&self.path)?;
+        file.write_all(&self.data)?;
+        if let Some(mtime) = self.mtime {
+            file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(mtime))?;
+        }
+        if let Some(mode) = self.mode {
+            file.set_permissions(Permissions::from_mode(mode & 0o777))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct WriterState {
+    pending: Mutex<usize>,
+    idle: Condvar,
+    failures: Mutex<Vec<String>>,
+}
+
+/// Writes files on worker threads while the archive is still being read, so that decompression
+/// and writes overlap. With a single thread, files are written as they are handed over.
+///
+/// Files are written in no particular order, so anything that depends on earlier entries, such
+/// as a link or a second entry for the same path, must [`FileWriter::wait`] for them first.
+pub struct FileWriter {
+    jobs: Option<SyncSender<FileJob>>,
+    state: Arc<WriterState>,
+    in_flight: HashSet<PathBuf>,
+}
+
+impl FileWriter {
+    pub fn new<'scope>(scope: &'scope Scope<'scope, '_>, threads: usize) -> Self {
+        let state = Arc::new(WriterState::default());
+        if threads <= 1 {
+            return Self {
+                jobs: None,
+                state,
+                in_flight: HashSet::new(),
+            };
+        }
+
+        let (jobs, receiver) = std::sync::mpsc::sync_channel(threads);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..threads {
+            let receiver = receiver.clone();
+            let state = state.clone();
+            scope.spawn(move || work(&receiver, &state));
+        }
+
+        Self {
+            jobs: Some(jobs),
+            state,
+            in_flight: HashSet::new(),
+        }
+    }
+
+    /// Whether files are written on other threads.
+    pub fn is_parallel(&self) -> bool {
+        self.jobs.is_some()
+    }
+
+    /// Whether a file for `path` has been handed over but may not be written yet.
+    pub fn is_pending(&self, path: &Path) -> bool {
+        self.in_flight.contains(path)
+    }
+
+    /// Writes `data` to `path`, replacing any file there. Failures are collected, and returned
+    /// by [`FileWriter::finish`].
+    pub fn write(
+        &mut self,
+        path: PathBuf,
+        name: String,
+        data: Vec<u8>,
+        mode: Option<u32>,
+        mtime: Option<u64>,
+    ) {
+        let job = FileJob {
+            path,
+            name,
+            data,
+            mode,
+            mtime,
+        };
+        let jobs = match &self.jobs {
+            Some(jobs) => jobs,
+            None => {
+                if let Err(e) = job.write() {
+                    let failure = format!("{} ({})", job.name, e);
+                    self.state.failures.lock().unwrap().push(failure);
+                }
+                return;
+            }
+        };
+
+        self.in_flight.insert(job.path.clone());
+        *self.state.pending.lock().unwrap() += 1;
+        // The workers only stop once the writer is finished.
+        jobs.send(job).expect("File writers stopped early");
+    }
+
+    /// Waits until every file handed over so far has been written.
+    pub fn wait(&mut self) {
+        let mut pending = self.state.pending.lock().unwrap();
+        while *pending > 0 {
+            pending = self.state.idle.wait(pending).unwrap();
+        }
+        self.in_flight.clear();
+    }
+
+    /// Waits for the remaining files and stops the workers, returning the files that could not
+    /// be written as "path (reason)".
+    pub fn finish(mut self) -> Vec<String> {
+        self.wait();
+        self.jobs = None;
+        std::mem::take(&mut *self.state.failures.lock().unwrap())
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<FileJob>>, state: &WriterState) {
+    loop {
+        let job = match receiver.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        if let Err(e) = job.write() {
+            let failure = format!("{} ({})", job.name, e);
+            state.failures.lock().unwrap().push(failure);
+        }
+
+        let mut pending = state.pending.lock().unwrap();
+        *pending -= 1;
+        if *pending == 0 {
+            state.idle.notify_all();
+        }
+    }
+}
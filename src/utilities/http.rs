@@ -0,0 +1,260 @@
+use std::future::Future;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Certificate, Client, IntoUrl, Proxy, RequestBuilder, Response, StatusCode, Url};
+
+use crate::config::NetworkConfig;
+use crate::error::Error;
+
+const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// Statuses that mean the server is overloaded, restarting or asking to slow down, so the same
+/// request may succeed later.
+const TRANSIENT_STATUSES: [StatusCode; 6] = [
+    StatusCode::REQUEST_TIMEOUT,
+    StatusCode::TOO_MANY_REQUESTS,
+    StatusCode::INTERNAL_SERVER_ERROR,
+    StatusCode::BAD_GATEWAY,
+    StatusCode::SERVICE_UNAVAILABLE,
+    StatusCode::GATEWAY_TIMEOUT,
+];
+
+/// The HTTP client shared by the sources and the downloader. Requests time out according to
+/// the network settings, and transient failures are retried with exponential backoff.
+#[derive(Clone)]
+pub struct HttpClient {
+    client: Client,
+    network: NetworkConfig,
+}
+
+impl HttpClient {
+    /// Builds the client with the configured proxies, certificates and user agent. Without a
+    /// configured proxy, the `HTTP_PROXY`/`HTTPS_PROXY`/`NO_PROXY` environment variables apply.
+    pub fn new(network: &NetworkConfig) -> Result<Self, Error> {
+        let mut builder = Client::builder()
+            .connect_timeout(seconds(network.connect_timeout))
+            .user_agent(network.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+
+        if network.http_proxy.is_some() || network.https_proxy.is_some() {
+            let http_proxy = parse_proxy(network.http_proxy.as_deref())?;
+            let https_proxy = parse_proxy(network.https_proxy.as_deref())?;
+            let no_proxy = network
+                .no_proxy
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .map(|host| host.trim().trim_start_matches('.').to_lowercase())
+                .filter(|host| !host.is_empty())
+                .collect::<Vec<_>>();
+
+            // reqwest's own `no_proxy` still sends the proxy's credentials to the hosts it
+            // excludes, so they are excluded here instead.
+            builder = builder.proxy(Proxy::custom(move |url| {
+                let host = url.host_str()?.to_lowercase();
+                if no_proxy
+                    .iter()
+                    .any(|excluded| bypasses_proxy(excluded, &host))
+                {
+                    return None;
+                }
+
+                match url.scheme() {
+                    "http" => http_proxy.clone(),
+                    "https" => https_proxy.clone(),
+                    _ => None,
+                }
+            }));
+        }
+
+        if let Some(ca_bundle) = &network.ca_bundle {
+            let pem = std::fs::read(ca_bundle)
+                .map_err(|e| config_error(format!("Cannot read {}: {}", ca_bundle.display(), e)))?;
+            let certificates = Certificate::from_pem_bundle(&pem).map_err(|e| {
+                config_error(format!("Invalid CA bundle {}: {}", ca_bundle.display(), e))
+            })?;
+            if certificates.is_empty() {
+                return Err(config_error(format!(
+                    "No certificates found in {}",
+                    ca_bundle.display()
+                )));
+            }
+
+            debug!(
+                "Trusting {} extra certificates from {}",
+                certificates.len(),
+                ca_bundle.display()
+            );
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let client = builder.build()?;
+
+        Ok(Self {
+            client,
+            network: network.clone(),
+        })
+    }
+
+    pub fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    /// Sends a request for a small response, such as an API call, retrying transient failures.
+    /// The whole response must arrive within the read timeout. A transient error status is
+    /// returned like any other once the retries run out, so callers can handle it.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, Error> {
+        let request = &request.timeout(seconds(self.network.read_timeout));
+        self.retry(move |last| async move {
+            let attempt = request
+                .try_clone()
+                .ok_or_else(|| Error::new("Request cannot be retried"))?;
+            let response = self.within(attempt.send()).await?;
+            match status_error(&response) {
+                Some(error) if !last && !self.waits_too_long(&error) => Err(error),
+                _ => Ok(response),
+            }
+        })
+        .await
+    }
+
+    /// Waits for a network operation, failing with a retryable error if it takes longer than the
+    /// read timeout. Downloads use this for each chunk, as they may take longer than that overall.
+    pub async fn within<T>(
+        &self,
+        operation: impl Future<Output = Result<T, reqwest::Error>>,
+    ) -> Result<T, Error> {
+        let timeout = seconds(self.network.read_timeout);
+        match tokio::time::timeout(timeout, operation).await {
+            Ok(result) => result.map_err(network_error),
+            Err(_) => Err(Error::Network {
+                message: format!("No response within {} seconds", timeout.as_secs_f64()),
+                retry_after: None,
+            }),
+        }
+    }
+
+    /// Runs `operation` until it succeeds or fails with an error other than [`Error::Network`],
+    /// waiting longer between each attempt. `operation` is told whether it is the last attempt.
+    pub async fn retry<T, F, Fut>(&self, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut(bool) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let last = attempt >= self.network.retries;
+            let error = match operation(last).await {
+                Err(error @ Error::Network { .. }) if !last && !self.waits_too_long(&error) => {
+                    error
+                }
+                result => return result,
+            };
+
+            let delay = match &error {
+                Error::Network {
+                    retry_after: Some(retry_after),
+                    ..
+                } => *retry_after,
+                _ => self.backoff(attempt),
+            };
+            attempt += 1;
+            warn!(
+                "{:?}, retrying in {:.1} seconds ({}/{})",
+                error,
+                delay.as_secs_f64(),
+                attempt,
+                self.network.retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Whether the server asked to wait longer than the longest delay between retries.
+    fn waits_too_long(&self, error: &Error) -> bool {
+        match error {
+            Error::Network {
+                retry_after: Some(retry_after),
+                ..
+            } => *retry_after > seconds(self.network.max_retry_delay),
+            _ => false,
+        }
+    }
+
+    /// The delay before retry number `attempt + 1`: the retry delay doubled for each earlier
+    /// retry, capped, with up to half of it taken off at random so that clients spread out.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = (self.network.retry_delay * 2f64.powi(attempt as i32))
+            .min(self.network.max_retry_delay);
+        seconds(delay * (1.0 - fastrand::f64() / 2.0))
+    }
+}
+
+/// Returns a retryable error if the response has a transient error status.
+pub fn status_error(response: &Response) -> Option<Error> {
+    let status = response.status();
+    if !TRANSIENT_STATUSES.contains(&status) {
+        return None;
+    }
+
+    Some(Error::Network {
+        message: format!("{} returned {}", response.url(), status),
+        retry_after: retry_after(response.headers()),
+    })
+}
+
+/// Reads a `Retry-After` header, given either in seconds or as a date.
+pub fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Connection failures, timeouts and interrupted responses are worth retrying; anything else,
+/// such as an invalid URL, is not.
+fn network_error(error: reqwest::Error) -> Error {
+    if error.is_connect() || error.is_timeout() || error.is_request() || error.is_body() {
+        Error::Network {
+            message: error.to_string(),
+            retry_after: None,
+        }
+    } else {
+        error.into()
+    }
+}
+
+fn parse_proxy(proxy: Option<&str>) -> Result<Option<Url>, Error> {
+    proxy
+        .map(|proxy| {
+            Url::parse(proxy).map_err(|e| config_error(format!("Invalid proxy {}: {}", proxy, e)))
+        })
+        .transpose()
+}
+
+/// Whether a `no_proxy` entry covers `host`: `*`, the host itself or one of its parent domains.
+fn bypasses_proxy(excluded: &str, host: &str) -> bool {
+    excluded == "*"
+        || host == excluded
+        || host
+            .strip_suffix(excluded)
+            .is_some_and(|subdomain| subdomain.ends_with('.'))
+}
+
+fn config_error(message: String) -> Error {
+    Error::Config(config::ConfigError::Message(message))
+}
+
+fn seconds(seconds: f64) -> Duration {
+    Duration::from_secs_f64(seconds.max(0.0))
+}
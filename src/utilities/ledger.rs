@@ -0,0 +1,176 @@
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use checksums::Algorithm;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Pinning;
+use crate::error::Error;
+use crate::models::release::Release;
+use crate::utilities::cache::Cache;
+
+/// The algorithm of the hashes pup computes to pin assets, whatever checksums a release has.
+pub const PIN_ALGORITHM: Algorithm = Algorithm::SHA2512;
+
+/// How many assets the ledger remembers.
+const LEDGER_ENTRIES: usize = 10_000;
+
+/// Identifies an asset across runs, e.g. `GE-Proton9-1.tar.gz` of release `GE-Proton9-1` from
+/// `@GloriousEggroll/proton-ge-custom`.
+#[derive(Debug, Clone)]
+pub struct AssetKey {
+    pub repository: String,
+    pub tag: String,
+    pub asset: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The hash an asset had the first time it was verified.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct LedgerEntry {
+    repository: String,
+    tag: String,
+    asset: String,
+    algorithm: String,
+    hash: String,
+    updated_at: DateTime<Utc>,
+    recorded_at: DateTime<Utc>,
+}
+
+impl PartialEq for LedgerEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.repository == other.repository && self.tag == other.tag && self.asset == other.asset
+    }
+}
+
+impl Eq for LedgerEntry {}
+
+impl Hash for LedgerEntry {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.repository.hash(state);
+        self.tag.hash(state);
+        self.asset.hash(state);
+    }
+}
+
+impl PartialOrd for LedgerEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.recorded_at.partial_cmp(&other.recorded_at)
+    }
+}
+
+/// Pins the hash of every asset the first time it is verified (trust on first use), so that an
+/// asset re-uploaded under the same tag is noticed.
+pub struct Ledger {
+    entries: Cache<LedgerEntry>,
+    policy: Pinning,
+}
+
+impl Ledger {
+    pub fn new(path: PathBuf, policy: Pinning) -> Self {
+        Self {
+            entries: Cache::new(path, LEDGER_ENTRIES),
+            policy,
+        }
+    }
+
+    fn find(&self, repository: &str, tag: &str, asset: &str) -> Option<&LedgerEntry> {
+        self.entries
+            .data
+            .iter()
+            .find(|e| e.repository == repository && e.tag == tag && e.asset == asset)
+    }
+
+    /// Records the hash of an asset the first time it is verified, and compares it to the record
+    /// afterwards. A different hash is a warning, or an error if pinning is set to refuse.
+    /// `hash` must be a [`PIN_ALGORITHM`] hash computed from the file itself, rather than one
+    /// published with the release, so that a re-upload cannot change how it is pinned.
+    pub fn check(&mut self, key: &AssetKey, hash: &str) -> Result<(), Error> {
+        if self.policy == Pinning::Off {
+            return Ok(());
+        }
+
+        let algorithm = format!("{:?}", PIN_ALGORITHM);
+        let mut entry = LedgerEntry {
+            repository: key.repository.clone(),
+            tag: key.tag.clone(),
+            asset: key.asset.clone(),
+            algorithm: algorithm.clone(),
+            hash: hash.to_string(),
+            updated_at: key.updated_at,
+            recorded_at: Utc::now(),
+        };
+
+        let recorded = match self.find(&key.repository, &key.tag, &key.asset) {
+            Some(recorded) => recorded.clone(),
+            None => {
+                debug!("Pinning {} of {} {}", key.asset, key.repository, key.tag);
+                return self.entries.update(entry);
+            }
+        };
+
+        // A hash of another algorithm cannot be compared, so it counts as a change.
+        if recorded.algorithm != algorithm || !recorded.hash.eq_ignore_ascii_case(hash) {
+            let error = Error::PinMismatch {
+                asset: describe(&key.asset, &key.repository, &key.tag),
+                expected: format!("{} {}", recorded.algorithm, recorded.hash),
+                actual: format!("{} {}", algorithm, hash),
+            };
+            return self.report(error);
+        }
+
+        if recorded.updated_at != key.updated_at {
+            warn!(
+                "{} was re-uploaded on {} with the same contents.",
+                describe(&key.asset, &key.repository, &key.tag),
+                key.updated_at.format("%Y-%m-%d %H:%M:%S UTC")
+            );
+            entry.recorded_at = recorded.recorded_at;
+            self.entries.update(entry)?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks for assets of `release` that were re-uploaded since they were pinned. This is a
+    /// warning, or an error if pinning is set to refuse, as the contents may have changed.
+    pub fn check_release(&self, repository: &str, release: &Release) -> Result<(), Error> {
+        if self.policy == Pinning::Off {
+            return Ok(());
+        }
+
+        for asset in &release.assets {
+            let recorded = match self.find(repository, &release.tag_name, &asset.name) {
+                Some(recorded) if recorded.updated_at != asset.updated_at => recorded,
+                _ => continue,
+            };
+
+            self.report(Error::PinMismatch {
+                asset: describe(&asset.name, repository, &release.tag_name),
+                expected: format!("an upload from {}", format_date(recorded.updated_at)),
+                actual: format!("a re-upload from {}", format_date(asset.updated_at)),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Fails with `error` if pinning is set to refuse, or logs it.
+    fn report(&self, error: Error) -> Result<(), Error> {
+        if self.policy == Pinning::Refuse {
+            return Err(error);
+        }
+
+        warn!("{:?}", error);
+        Ok(())
+    }
+}
+
+fn describe(asset: &str, repository: &str, tag: &str) -> String {
+    format!("{} of {} {}", asset, repository, tag)
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format("%Y-%m-%d %H:%M:%S UTC").to_string()
+}
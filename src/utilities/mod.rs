@@ -1,3 +1,13 @@
+pub(crate) mod api_client;
 pub(crate) mod cache;
+pub(crate) mod checksum;
 pub(crate) mod downloader;
 pub(crate) mod extract;
+pub(crate) mod file_writer;
+pub(crate) mod http;
+pub(crate) mod ledger;
+pub(crate) mod pattern;
+pub(crate) mod scanner;
+pub(crate) mod signature;
+pub(crate) mod staging;
+pub(crate) mod token;
@@ -0,0 +1,53 @@
+use crate::error::Error;
+
+/// Matches `text` against a glob pattern where `*` matches any run of characters and `?` matches
+/// a single character.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A pattern matched against asset names: a regular expression when prefixed with `re:`,
+/// otherwise a glob that must match the whole name.
+pub enum NamePattern {
+    Glob(String),
+    Regex(regex::Regex),
+}
+
+impl NamePattern {
+    pub fn parse(pattern: &str) -> Result<Self, Error> {
+        match pattern.strip_prefix("re:") {
+            Some(expression) => regex::Regex::new(expression)
+                .map(NamePattern::Regex)
+                .map_err(|e| Error::Unspecified(format!("Invalid pattern {}: {}", pattern, e))),
+            None => Ok(NamePattern::Glob(pattern.to_string())),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Glob(pattern) => glob_match(pattern, name),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
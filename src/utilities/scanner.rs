@@ -0,0 +1,111 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+/// A compatibility tool directory found in an install directory.
+pub struct ToolDirectory {
+    pub dir_name: String,
+    pub path: PathBuf,
+    /// Names the tool reports for itself, most specific first, ending with the directory name.
+    pub names: Vec<String>,
+}
+
+/// Lists the tool directories in `install_dir`, skipping symlinks and plain files.
+pub fn scan(install_dir: &Path) -> Result<Vec<ToolDirectory>, Error> {
+    if !install_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut directories = Vec::new();
+    for entry in std::fs::read_dir(install_dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if !file_type.is_dir() || file_type.is_symlink() {
+            continue;
+        }
+
+        let dir_name = match entry.file_name().to_str() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        let path = entry.path();
+        let mut names: Vec<String> = Vec::new();
+        for name in read_version_file(&path.join("version"))
+            .into_iter()
+            .chain(read_vdf_names(&path.join("compatibilitytool.vdf")))
+            .chain([dir_name.clone()])
+        {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+
+        debug!("Found tool directory {} with names {:?}", dir_name, names);
+        directories.push(ToolDirectory {
+            dir_name,
+            path,
+            names,
+        });
+    }
+
+    directories.sort_by(|a, b| a.dir_name.cmp(&b.dir_name));
+    Ok(directories)
+}
+
+/// Finds the tag that best matches one of the directory's names. Exact matches win over tags
+/// embedded in a longer name (e.g. `lutris-GE-Proton8-26-x86_64`), and longer tags win over
+/// shorter ones.
+pub fn match_tag<'a>(
+    directory: &ToolDirectory,
+    tags: impl Iterator<Item = &'a str> + Clone,
+) -> Option<&'a str> {
+    for name in &directory.names {
+        if let Some(tag) = tags.clone().find(|tag| tag == name) {
+            return Some(tag);
+        }
+    }
+
+    directory
+        .names
+        .iter()
+        .flat_map(|name| tags.clone().filter(|tag| contains_tag(name, tag)))
+        .max_by_key(|tag| tag.len())
+}
+
+fn contains_tag(name: &str, tag: &str) -> bool {
+    name.match_indices(tag).any(|(start, _)| {
+        let before = name[..start].chars().next_back();
+        let after = name[start + tag.len()..].chars().next();
+        !before.is_some_and(|c| c.is_alphanumeric()) && !after.is_some_and(|c| c.is_alphanumeric())
+    })
+}
+
+/// Reads the name from a `version` file, which contains a timestamp followed by the name.
+fn read_version_file(path: &Path) -> Option<String> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    contents.split_whitespace().last().map(String::from)
+}
+
+/// Reads the internal and display names from a `compatibilitytool.vdf` file.
+fn read_vdf_names(path: &Path) -> Vec<String> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return Vec::new(),
+    };
+
+    let tokens = contents.split('"').skip(1).step_by(2).collect::<Vec<_>>();
+
+    let token_after = |key: &str| {
+        tokens
+            .iter()
+            .position(|t| t.eq_ignore_ascii_case(key))
+            .and_then(|i| tokens.get(i + 1))
+            .map(|t| t.to_string())
+    };
+
+    let mut names = Vec::new();
+    names.extend(token_after("compat_tools"));
+    names.extend(token_after("display_name"));
+    names
+}
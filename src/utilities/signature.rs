@@ -0,0 +1,198 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::config::ConfigModule;
+use crate::error::Error;
+
+/// The kinds of detached signatures pup can check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureKind {
+    /// A `.minisig` file.
+    Minisign,
+    /// A binary `.sig` or armored `.asc` file, checked with `gpgv`.
+    OpenPgp,
+}
+
+/// A detached signature downloaded from a release.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub kind: SignatureKind,
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+/// The public keys a module trusts to sign its releases.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedKeys {
+    /// Minisign public keys, or paths to minisign `.pub` files.
+    pub minisign: Vec<String>,
+    /// OpenPGP public key files, binary or armored.
+    pub pgp: Vec<PathBuf>,
+}
+
+impl TrustedKeys {
+    pub fn from_config(config: &ConfigModule) -> Self {
+        Self {
+            minisign: config.minisign_keys.clone(),
+            pgp: config.pgp_keys.clone(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.minisign.is_empty() && self.pgp.is_empty()
+    }
+
+    /// Whether there is a key to check a signature of this kind with.
+    pub fn supports(&self, kind: SignatureKind) -> bool {
+        match kind {
+            SignatureKind::Minisign => !self.minisign.is_empty(),
+            SignatureKind::OpenPgp => !self.pgp.is_empty(),
+        }
+    }
+}
+
+/// Returns the kind of signature `name` is if it is a detached signature for `filename`, such as
+/// `GE-Proton9-1.tar.gz.minisig`.
+pub fn signature_kind(name: &str, filename: &str) -> Option<SignatureKind> {
+    match name.strip_prefix(filename)?.strip_prefix('.')? {
+        "minisig" => Some(SignatureKind::Minisign),
+        "sig" | "asc" => Some(SignatureKind::OpenPgp),
+        _ => None,
+    }
+}
+
+/// Checks that `signature` is a valid signature of the file at `path` by one of `keys`. A
+/// signature of a kind none of `keys` can check counts as missing. Runs `gpgv`, so call it on a
+/// blocking thread.
+pub fn verify(path: &Path, signature: &Signature, keys: &TrustedKeys) -> Result<(), Error> {
+    if !keys.supports(signature.kind) {
+        let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        return Err(Error::SignatureMissing(format!(
+            "{} ({} cannot be checked with the trusted keys)",
+            filename, signature.name
+        )));
+    }
+
+    match signature.kind {
+        SignatureKind::Minisign => verify_minisign(path, signature, keys),
+        SignatureKind::OpenPgp => verify_openpgp(path, signature, keys),
+    }
+}
+
+fn verify_minisign(path: &Path, signature: &Signature, keys: &TrustedKeys) -> Result<(), Error> {
+    let invalid =
+        |reason: String| Error::SignatureInvalid(format!("{}: {}", signature.name, reason));
+    let decoded = minisign_verify::Signature::decode(&String::from_utf8_lossy(&signature.data))
+        .map_err(|e| invalid(e.to_string()))?;
+
+    let mut reason = "no trusted minisign key".to_string();
+    for key in &keys.minisign {
+        let key = match Path::new(key).is_file() {
+            true => minisign_verify::PublicKey::from_file(key),
+            false => minisign_verify::PublicKey::from_base64(key),
+        }
+        .map_err(|e| Error::Unspecified(format!("Invalid minisign key {}: {}", key, e)))?;
+
+        match verify_minisign_with(path, &decoded, &key) {
+            Ok(()) => return Ok(()),
+            Err(minisign_verify::Error::UnexpectedKeyId) => {
+                reason = "signed by an untrusted key".to_string()
+            }
+            Err(e) => reason = e.to_string(),
+        }
+    }
+
+    Err(invalid(reason))
+}
+
+fn verify_minisign_with(
+    path: &Path,
+    signature: &minisign_verify::Signature,
+    key: &minisign_verify::PublicKey,
+) -> Result<(), minisign_verify::Error> {
+    let mut verifier = match key.verify_stream(signature) {
+        Ok(verifier) => verifier,
+        // Signatures from old minisign versions sign the whole file rather than its hash.
+        Err(minisign_verify::Error::UnsupportedLegacyMode) => {
+            return key.verify(&std::fs::read(path)?, signature, true)
+        }
+        Err(e) => return Err(e),
+    };
+
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        verifier.update(&buffer[..read]);
+    }
+
+    verifier.finalize()
+}
+
+/// Checks an OpenPGP signature with `gpgv`, using a keyring made of only the trusted keys.
+fn verify_openpgp(path: &Path, signature: &Signature, keys: &TrustedKeys) -> Result<(), Error> {
+    let mut keyring = Vec::new();
+    for key in &keys.pgp {
+        let data = std::fs::read(key)?;
+        keyring.extend(match data.starts_with(b"-----BEGIN") {
+            true => dearmor(key)?,
+            false => data,
+        });
+    }
+
+    let keyring_path = path.with_file_name(format!(".{}.keyring", signature.name));
+    let signature_path = path.with_file_name(format!(".{}", signature.name));
+    std::fs::write(&keyring_path, keyring)?;
+    std::fs::write(&signature_path, &signature.data)?;
+
+    let output = Command::new("gpgv")
+        .arg("--keyring")
+        .arg(&keyring_path)
+        .arg(&signature_path)
+        .arg(path)
+        .output();
+
+    let _ = std::fs::remove_file(&keyring_path);
+    let _ = std::fs::remove_file(&signature_path);
+
+    let output = output.map_err(|e| Error::Unspecified(format!("Could not run gpgv: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let reason = stderr
+            .lines()
+            .find(|line| line.contains("BAD signature") || line.contains("Can't check"))
+            .unwrap_or("verification failed")
+            .trim_start_matches("gpgv: ");
+        return Err(Error::SignatureInvalid(format!(
+            "{}: {}",
+            signature.name, reason
+        )));
+    }
+
+    Ok(())
+}
+
+/// Converts an armored OpenPGP key to the binary format `gpgv` reads keyrings in.
+fn dearmor(key: &Path) -> Result<Vec<u8>, Error> {
+    let output = Command::new("gpg")
+        .arg("--dearmor")
+        .arg("--output")
+        .arg("-")
+        .arg(key)
+        .output()
+        .map_err(|e| Error::Unspecified(format!("Could not run gpg: {}", e)))?;
+
+    if !output.status.success() {
+        return Err(Error::Unspecified(format!(
+            "Could not read OpenPGP key {}",
+            key.display()
+        )));
+    }
+
+    Ok(output.stdout)
+}
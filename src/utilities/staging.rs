@@ -0,0 +1,157 @@
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+
+/// What Steam needs to recognize a compatibility tool.
+const STEAM_REQUIRED_FILES: [&str; 2] = ["proton", "compatibilitytool.vdf"];
+
+/// An install that is extracted next to `install_dir` and only moved into it once it has been
+/// validated. Until [`StagedInstall::commit`] succeeds, `install_dir` is left untouched; the
+/// staging directory is removed when the install is dropped.
+pub struct StagedInstall {
+    install_dir: PathBuf,
+    staging_dir: PathBuf,
+    /// Set if a replaced entry could not be restored, so it must not be deleted with the rest.
+    keep_staging: bool,
+}
+
+impl StagedInstall {
+    /// Creates an empty staging directory beside `install_dir`, on the same filesystem so that
+    /// moving the install into place is a rename.
+    pub fn new(install_dir: &Path) -> Result<Self, Error> {
+        std::fs::create_dir_all(install_dir)?;
+        let name = install_dir
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let staging_dir = install_dir.parent().unwrap_or(install_dir).join(format!(
+            ".{}.staging-{}",
+            name,
+            std::process::id()
+        ));
+
+        if staging_dir.exists() {
+            std::fs::remove_dir_all(&staging_dir)?;
+        }
+        std::fs::create_dir_all(staging_dir.join("new"))?;
+        std::fs::create_dir_all(staging_dir.join("previous"))?;
+
+        Ok(Self {
+            install_dir: install_dir.to_path_buf(),
+            staging_dir,
+            keep_staging: false,
+        })
+    }
+
+    /// Where to extract the new install to.
+    pub fn path(&self) -> PathBuf {
+        self.staging_dir.join("new")
+    }
+
+    /// Checks that every directory in `contents` has the `required` files. Without an explicit
+    /// list, tools installed into Steam's `compatibilitytools.d` need what Steam looks for.
+    pub fn validate(&self, contents: &[String], required: Option<&[String]>) -> Result<(), Error> {
+        if contents.is_empty() {
+            return Err(Error::InvalidInstall(vec![
+                "the archive is empty".to_string()
+            ]));
+        }
+
+        let steam_required = STEAM_REQUIRED_FILES.map(String::from);
+        let required = match required {
+            Some(required) => required,
+            None if self.install_dir.ends_with("compatibilitytools.d") => &steam_required[..],
+            None => &[],
+        };
+
+        let mut missing = Vec::new();
+        for name in contents {
+            let path = self.path().join(name);
+            if !path.is_dir() {
+                continue;
+            }
+
+            missing.extend(
+                required
+                    .iter()
+                    .filter(|file| !path.join(file).exists())
+                    .map(|file| format!("{}/{}", name, file)),
+            );
+        }
+
+        match missing.is_empty() {
+            true => Ok(()),
+            false => Err(Error::InvalidInstall(missing)),
+        }
+    }
+
+    /// Moves `contents` into the install directory, replacing what is there. The replaced entries
+    /// are kept until every entry has been moved, and put back if any move fails.
+    pub fn commit(&mut self, contents: &[String]) -> Result<(), Error> {
+        let mut replaced = Vec::new();
+        let mut moved = Vec::new();
+        let result = self.move_into_place(contents, &mut replaced, &mut moved);
+
+        if let Err(e) = &result {
+            warn!("Could not install, rolling back: {:?}", e);
+            self.roll_back(&replaced, &moved);
+        }
+        result
+    }
+
+    fn move_into_place<'a>(
+        &self,
+        contents: &'a [String],
+        replaced: &mut Vec<&'a String>,
+        moved: &mut Vec<&'a String>,
+    ) -> Result<(), Error> {
+        for name in contents {
+            let target = self.install_dir.join(name);
+            if target.symlink_metadata().is_ok() {
+                debug!("Moving the previous {} aside", target.display());
+                std::fs::rename(&target, self.staging_dir.join("previous").join(name))?;
+                replaced.push(name);
+            }
+
+            std::fs::rename(self.path().join(name), &target)?;
+            moved.push(name);
+        }
+
+        Ok(())
+    }
+
+    fn roll_back(&mut self, replaced: &[&String], moved: &[&String]) {
+        for name in moved {
+            let result = std::fs::rename(self.install_dir.join(name), self.path().join(name));
+            if let Err(e) = result {
+                error!("Could not remove the new {}: {}", name, e);
+            }
+        }
+
+        for name in replaced {
+            let previous = self.staging_dir.join("previous").join(name);
+            if let Err(e) = std::fs::rename(&previous, self.install_dir.join(name)) {
+                self.keep_staging = true;
+                error!(
+                    "Could not restore {}, it is kept at {}: {}",
+                    name,
+                    previous.display(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+impl Drop for StagedInstall {
+    fn drop(&mut self) {
+        if self.keep_staging {
+            return;
+        }
+
+        debug!("Removing {}", self.staging_dir.display());
+        if let Err(e) = std::fs::remove_dir_all(&self.staging_dir) {
+            warn!("Could not remove {}: {}", self.staging_dir.display(), e);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::config::ConfigModule;
+
+const TOKEN_VARIABLES: [&str; 2] = ["PUP_GITHUB_TOKEN", "GITHUB_TOKEN"];
+/// The only host the environment variables are sent to.
+const PUBLIC_HOST: &str = "github.com";
+
+/// Looks for a GitHub token for `host`, in order: the `PUP_GITHUB_TOKEN` and `GITHUB_TOKEN`
+/// environment variables, the module's `token_command`, and the `gh` CLI's `hosts.yml`. The
+/// environment variables hold a token for github.com, so they are ignored for other hosts such as
+/// GitHub Enterprise servers.
+pub fn discover_github_token(config: &ConfigModule, host: &str) -> Option<String> {
+    if host == PUBLIC_HOST {
+        for variable in TOKEN_VARIABLES {
+            if let Some(token) = std::env::var(variable).ok().filter(|t| !t.is_empty()) {
+                debug!("Using GitHub token from ${}", variable);
+                return Some(token);
+            }
+        }
+    }
+
+    if let Some(command) = &config.token_command {
+        match run_token_command(command) {
+            Some(token) => {
+                debug!("Using GitHub token from token_command");
+                return Some(token);
+            }
+            None => warn!("token_command did not produce a token, ignoring it."),
+        }
+    }
+
+    let token = read_gh_hosts_token(host);
+    if token.is_some() {
+        debug!("Using GitHub token from the gh CLI config for {}", host);
+    }
+
+    token
+}
+
+fn run_token_command(command: &str) -> Option<String> {
+    let output = Command::new("sh").arg("-c").arg(command).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!token.is_empty()).then_some(token)
+}
+
+fn gh_config_dir() -> Option<PathBuf> {
+    if let Ok(dir) = std::env::var("GH_CONFIG_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+
+    if let Ok(dir) = std::env::var("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("gh"));
+    }
+
+    dirs::home_dir().map(|home| home.join(".config").join("gh"))
+}
+
+/// Reads `oauth_token` from the `host` section of `hosts.yml`, which looks like:
+///
+/// ```yaml
+/// github.com:
+///     oauth_token: gho_...
+///     user: octocat
+/// ```
+fn read_gh_hosts_token(host: &str) -> Option<String> {
+    let hosts = std::fs::read_to_string(gh_config_dir()?.join("hosts.yml")).ok()?;
+
+    let mut in_host = false;
+    for line in hosts.lines() {
+        if !line.starts_with(char::is_whitespace) {
+            in_host = line.trim_end().trim_end_matches(':') == host;
+            continue;
+        }
+
+        if let Some(token) = line.trim().strip_prefix("oauth_token:") {
+            if in_host {
+                let token = token.trim().trim_matches(|c| c == '"' || c == '\'');
+                return (!token.is_empty()).then(|| token.to_string());
+            }
+        }
+    }
+
+    None
+}
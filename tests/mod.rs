@@ -1,17 +1,2190 @@
-use pup_rs::config::Config;
-use pup_rs::proton_manager::ProtonManager;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Once;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use pup_rs::config::{Channel, Config, ConfigModule, NetworkConfig, Pinning, SourceType};
+use pup_rs::models::installed::InstallState;
+use pup_rs::models::version::Version;
+use pup_rs::proton_manager::{channel_sink, ProgressPhase, ProtonManager};
+use serde_json::{json, Value};
 use tokio::test;
+use wiremock::matchers::{header, method, path, path_regex, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
-fn get_manager() -> ProtonManager {
-    let config = Config::new(Option::from("tests/config.test.toml".to_string()));
+/// A module whose install and cache directories are inside `dir`. Module state goes to the
+/// user's cache directory, which is moved under the target directory so tests never touch the
+/// user's own.
+fn test_config(dir: &Path) -> ConfigModule {
+    static CACHE_HOME: Once = Once::new();
+    CACHE_HOME.call_once(|| {
+        std::env::set_var(
+            "XDG_CACHE_HOME",
+            Path::new(env!("CARGO_TARGET_TMPDIR")).join("cache"),
+        )
+    });
+
+    let config = ConfigModule {
+        install_dir: dir.join("install"),
+        cache_dir: dir.join("cache"),
+        ..Default::default()
+    };
+    std::fs::create_dir_all(&config.cache_dir).unwrap();
+    config
+}
+
+/// A manager for a module named after the test's temporary directory, so tests running at the
+/// same time keep their module state apart.
+fn new_manager(config: &ConfigModule) -> ProtonManager {
+    let name = config.install_dir.parent().unwrap().file_name().unwrap();
+    ProtonManager::new(name.to_str().unwrap().to_string(), config).unwrap()
+}
+
+/// A release as returned by the GitHub API, with an asset for each of `assets`.
+fn github_release(server: &MockServer, tag: &str, published_at: &str, assets: &[&str]) -> Value {
+    let user = json!({
+        "login": "octocat",
+        "id": 1,
+        "node_id": "MDQ6VXNlcjE=",
+        "avatar_url": "https://github.com/images/error/octocat_happy.gif",
+        "gravatar_id": "",
+        "url": "https://api.github.com/users/octocat",
+        "html_url": "https://github.com/octocat",
+        "followers_url": "https://api.github.com/users/octocat/followers",
+        "following_url": "https://api.github.com/users/octocat/following",
+        "gists_url": "https://api.github.com/users/octocat/gists",
+        "starred_url": "https://api.github.com/users/octocat/starred",
+        "subscriptions_url": "https://api.github.com/users/octocat/subscriptions",
+        "organizations_url": "https://api.github.com/users/octocat/orgs",
+        "repos_url": "https://api.github.com/users/octocat/repos",
+        "events_url": "https://api.github.com/users/octocat/events",
+        "received_events_url": "https://api.github.com/users/octocat/received_events",
+        "type": "User",
+        "site_admin": false
+    });
+
+    let assets = assets
+        .iter()
+        .enumerate()
+        .map(|(id, name)| {
+            json!({
+                "url": format!("{}/assets/{}", server.uri(), id),
+                "browser_download_url": format!("{}/download/{}/{}", server.uri(), tag, name),
+                "id": id,
+                "node_id": "",
+                "name": name,
+                "label": null,
+                "state": "uploaded",
+                "content_type": "application/octet-stream",
+                "size": 0,
+                "download_count": 0,
+                "created_at": published_at,
+                "updated_at": published_at,
+                "uploader": user
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "url": format!("{}/releases/{}", server.uri(), tag),
+        "html_url": format!("{}/releases/{}", server.uri(), tag),
+        "assets_url": format!("{}/releases/{}/assets", server.uri(), tag),
+        "upload_url": "",
+        "tarball_url": null,
+        "zipball_url": null,
+        "id": 1,
+        "node_id": "",
+        "tag_name": tag,
+        "target_commitish": "master",
+        "name": tag,
+        "body": null,
+        "draft": false,
+        "prerelease": false,
+        "created_at": published_at,
+        "published_at": published_at,
+        "author": user,
+        "assets": assets
+    })
+}
+
+/// Builds a `.tar.gz` containing a `{top_level}/` directory with a single `proton` file.
+fn make_archive(top_level: &str) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Directory);
+    header.set_size(0);
+    header.set_mode(0o755);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, top_level, std::io::empty())
+        .unwrap();
+
+    let contents = b"#!/usr/bin/env python3\n";
+    let mut header = tar::Header::new_gnu();
+    header.set_size(contents.len() as u64);
+    header.set_mode(0o755);
+    header.set_cksum();
+    builder
+        .append_data(
+            &mut header,
+            Path::new(top_level).join("proton"),
+            &contents[..],
+        )
+        .unwrap();
+    builder.into_inner().unwrap().finish().unwrap()
+}
 
-    let module = config.modules.iter().next().unwrap();
-    ProtonManager::new(module.0.clone(), module.1)
+fn sha512(data: &[u8]) -> String {
+    hash(data, checksums::Algorithm::SHA2512)
+}
+
+fn hash(data: &[u8], algorithm: checksums::Algorithm) -> String {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(data).unwrap();
+    checksums::hash_file(file.path(), algorithm)
 }
 
 #[test]
 async fn test_get_releases() {
-    let mut manager = get_manager();
+    let server = MockServer::start().await;
+    let release = github_release(
+        &server,
+        "GE-Proton9-1",
+        "2024-01-01T00:00:00Z",
+        &["GE-Proton9-1.tar.gz", "GE-Proton9-1.sha512sum"],
+    );
+    Mock::given(method("GET"))
+        .and(path(
+            "/api/v3/repos/GloriousEggroll/proton-ge-custom/releases",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([release])))
+        .mount(&server)
+        .await;
+
+    // The module from the test config, keeping its directories out of /tmp/pup-rs.
+    let config = Config::new(Option::from("tests/config.test.toml".to_string()));
+    let module = config.modules.values().next().unwrap();
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&ConfigModule {
+        owner: module.owner.clone(),
+        repo: module.repo.clone(),
+        network: module.network.clone(),
+        api_base: Some(format!("{}/api/v3", server.uri())),
+        ..test_config(dir.path())
+    });
     let releases = manager.get_releases(1, false).await.unwrap();
     assert!(!releases.is_empty());
 }
+
+#[test]
+async fn test_gitlab_source() {
+    let server = MockServer::start().await;
+    let release = json!({
+        "name": "Wine GE 8-26",
+        "tag_name": "GE-Proton8-26",
+        "created_at": "2023-12-01T00:00:00Z",
+        "released_at": "2023-12-01T00:00:00Z",
+        "assets": {
+            "links": [{
+                "name": "wine-lutris-GE-Proton8-26-x86_64.tar.xz",
+                "url": format!("{}/files/wine-lutris-GE-Proton8-26-x86_64.tar.xz", server.uri()),
+                "direct_asset_url": null
+            }]
+        }
+    });
+
+    Mock::given(method("GET"))
+        .and(path("/api/v4/projects/group%2Fwine-ge/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([release])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(
+            "/api/v4/projects/group%2Fwine-ge/releases/GE-Proton8-26",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&release))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let config = ConfigModule {
+        source: SourceType::GitLab,
+        host: Some(server.uri()),
+        project: Some("group/wine-ge".to_string()),
+        ..test_config(dir.path())
+    };
+    let mut manager = new_manager(&config);
+
+    let releases = manager.get_releases(10, false).await.unwrap();
+    assert!(releases.iter().any(|r| r.tag_name == "GE-Proton8-26"));
+
+    let release = manager.get_release("GE-Proton8-26").await.unwrap();
+    assert_eq!(release.assets.len(), 1);
+    assert_eq!(
+        release.assets[0].name,
+        "wine-lutris-GE-Proton8-26-x86_64.tar.xz"
+    );
+}
+
+#[test]
+async fn test_gitea_install_and_remove() {
+    let server = MockServer::start().await;
+    let archive = make_archive("GE-Proton9-1");
+    let release = json!({
+        "name": "GE-Proton9-1",
+        "tag_name": "GE-Proton9-1",
+        "created_at": "2024-01-01T00:00:00Z",
+        "published_at": "2024-01-01T00:00:00Z",
+        "assets": [
+            {
+                "name": "GE-Proton9-1.tar.gz",
+                "browser_download_url": format!("{}/files/GE-Proton9-1.tar.gz", server.uri()),
+                "created_at": "2024-01-01T00:00:00Z"
+            },
+            {
+                "name": "GE-Proton9-1.sha512sum",
+                "browser_download_url": format!("{}/files/GE-Proton9-1.sha512sum", server.uri()),
+                "created_at": "2024-01-01T00:00:00Z"
+            }
+        ]
+    });
+
+    Mock::given(method("GET"))
+        .and(path(
+            "/api/v1/repos/owner/proton-fork/releases/tags/GE-Proton9-1",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&release))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/files/GE-Proton9-1.sha512sum"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(format!("{}  GE-Proton9-1.tar.gz\n", sha512(&archive))),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/files/GE-Proton9-1.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(archive))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let config = ConfigModule {
+        owner: "owner".to_string(),
+        repo: "proton-fork".to_string(),
+        symlink: Some("proton-latest".to_string()),
+        source: SourceType::Gitea,
+        host: Some(server.uri()),
+        ..test_config(dir.path())
+    };
+    let mut manager = new_manager(&config);
+
+    manager.install_release("GE-Proton9-1").await.unwrap();
+    let installed_dir = config.install_dir.join("GE-Proton9-1");
+    assert!(installed_dir.join("proton").is_file());
+    assert!(config.install_dir.join("proton-latest").is_symlink());
+
+    manager.remove_release("GE-Proton9-1", false).await.unwrap();
+    assert!(!installed_dir.exists());
+    assert!(!config.install_dir.join("proton-latest").exists());
+    assert!(!config.cache_dir.join("GE-Proton9-1.tar.gz").exists());
+}
+
+#[test]
+async fn test_http_directory_listing() {
+    let server = MockServer::start().await;
+    let archive = make_archive("GE-Proton9-2");
+    let listing = r#"<html><body><h1>Index of /proton/</h1><hr><pre><a href="../">../</a>
+<a href="GE-Proton9-1/">GE-Proton9-1/</a>                                      01-Jan-2024 10:00       -
+<a href="GE-Proton9-2.sha512sum">GE-Proton9-2.sha512sum</a>                    02-Feb-2024 10:00     150
+<a href="GE-Proton9-2.tar.gz">GE-Proton9-2.tar.gz</a>                          02-Feb-2024 10:00  419430
+</pre><hr></body></html>"#;
+    let subdirectory_listing = r#"<html><body><pre><a href="../">../</a>
+<a href="GE-Proton9-1.tar.gz">GE-Proton9-1.tar.gz</a>                          01-Jan-2024 10:00  419430
+</pre></body></html>"#;
+
+    Mock::given(method("GET"))
+        .and(path("/proton/releases.json"))
+        .respond_with(ResponseTemplate::new(404))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/proton/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(listing))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/proton/GE-Proton9-1/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(subdirectory_listing))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/proton/GE-Proton9-2.sha512sum"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(format!("{}  GE-Proton9-2.tar.gz\n", sha512(&archive))),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/proton/GE-Proton9-2.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(archive))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let config = ConfigModule {
+        source: SourceType::Http,
+        host: Some(format!("{}/proton", server.uri())),
+        ..test_config(dir.path())
+    };
+    let mut manager = new_manager(&config);
+
+    let releases = manager.fetch_releases(10).await.unwrap();
+    let tags = releases
+        .iter()
+        .map(|r| r.tag_name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(tags, vec!["GE-Proton9-2", "GE-Proton9-1"]);
+    assert_eq!(releases[0].assets.len(), 2);
+    assert_eq!(
+        releases[1].assets[0].browser_download_url.path(),
+        "/proton/GE-Proton9-1/GE-Proton9-1.tar.gz"
+    );
+
+    manager.install_release("GE-Proton9-2").await.unwrap();
+    assert!(config.install_dir.join("GE-Proton9-2/proton").is_file());
+}
+
+#[test]
+async fn test_http_directory_listing_pages() {
+    let server = MockServer::start().await;
+    let mut listing = String::from("<html><body><pre><a href=\"../\">../</a>\n");
+    for i in 1..=250 {
+        let date = chrono::DateTime::from_timestamp(1704067200 + i * 3600, 0).unwrap();
+        listing.push_str(&format!(
+            "<a href=\"GE-Proton9-{0}/\">GE-Proton9-{0}/</a> {1} -\n",
+            i,
+            date.format("%d-%b-%Y %H:%M")
+        ));
+    }
+
+    // The top-level listing is read once for the whole stream, and each subdirectory once.
+    Mock::given(method("GET"))
+        .and(path("/proton/releases.json"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/proton/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(listing))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path_regex("^/proton/GE-Proton9-[0-9]+/$"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(r#"<a href="proton.tar.gz">proton.tar.gz</a>"#),
+        )
+        .expect(250)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&ConfigModule {
+        source: SourceType::Http,
+        host: Some(format!("{}/proton", server.uri())),
+        ..test_config(dir.path())
+    });
+    let mut stream = manager.stream_releases(100, 1);
+    let mut tags = Vec::new();
+    while let Some(release) = stream.next().await.unwrap() {
+        tags.push(release.tag_name);
+    }
+    assert_eq!(tags.len(), 250);
+    assert_eq!(tags[0], "GE-Proton9-250");
+    assert_eq!(tags[249], "GE-Proton9-1");
+}
+
+#[test]
+async fn test_undated_releases() {
+    let server = MockServer::start().await;
+    let manifest = json!([{
+        "tag_name": "GE-Proton9-1",
+        "assets": [{ "name": "GE-Proton9-1.tar.gz" }]
+    }]);
+    Mock::given(method("GET"))
+        .and(path("/proton/releases.json"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(manifest))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let config = format!("source = \"http\"\nhost = \"{}/proton\"\n", server.uri());
+    let (code, stderr) = run_pup(dir.path(), &config, &["list"]).await;
+    assert_eq!(code, Some(0), "{}", stderr);
+    let (code, stderr) = run_pup(dir.path(), &config, &[]).await;
+    assert_eq!(code, Some(0), "{}", stderr);
+    assert!(
+        stderr.contains("The latest release GE-Proton9-1 is not installed."),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+async fn test_local_source_install() {
+    let dir = tempfile::tempdir().unwrap();
+    let local_dir = dir.path().join("archives");
+    std::fs::create_dir_all(&local_dir).unwrap();
+    let archive = make_archive("GE-Proton9-3");
+    std::fs::write(
+        local_dir.join("GE-Proton9-3.sha512sum"),
+        format!("{}  GE-Proton9-3.tar.gz\n", sha512(&archive)),
+    )
+    .unwrap();
+    std::fs::write(local_dir.join("GE-Proton9-3.tar.gz"), archive).unwrap();
+
+    let config = ConfigModule {
+        symlink: Some("proton-latest".to_string()),
+        source: SourceType::Local,
+        local_dir: Some(local_dir.clone()),
+        ..test_config(dir.path())
+    };
+    let mut manager = new_manager(&config);
+
+    let release = manager.get_release("GE-Proton9-3").await.unwrap();
+    assert_eq!(release.assets.len(), 2);
+    assert_eq!(release.assets[0].browser_download_url.scheme(), "file");
+
+    manager.install_release("GE-Proton9-3").await.unwrap();
+    assert!(config.install_dir.join("GE-Proton9-3/proton").is_file());
+    assert!(config.install_dir.join("proton-latest").is_symlink());
+    assert!(local_dir.join("GE-Proton9-3.tar.gz").is_file());
+}
+
+#[test]
+async fn test_local_source_pages() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&local_archive_config(
+        dir.path(),
+        "GE-Proton9-1.tar.gz",
+        b"",
+    ));
+    for i in 2..=300 {
+        add_local_archive(dir.path(), &format!("GE-Proton9-{}.tar.gz", i), b"");
+    }
+
+    let mut stream = manager.stream_releases(100, 1);
+    let mut tags = Vec::new();
+    while let Some(release) = stream.next().await.unwrap() {
+        tags.push(release.tag_name);
+    }
+    assert_eq!(tags.len(), 300);
+    assert_eq!(tags[0], "GE-Proton9-300");
+    assert_eq!(tags[299], "GE-Proton9-1");
+}
+
+#[test]
+async fn test_github_private_assets_with_token() {
+    // Meant for github.com, so never sent to the mock server.
+    std::env::set_var("PUP_GITHUB_TOKEN", "github-com-token");
+    std::env::remove_var("GITHUB_TOKEN");
+
+    let server = MockServer::start().await;
+    let archive = make_archive("GE-Proton9-4");
+    let release = github_release(
+        &server,
+        "GE-Proton9-4",
+        "2024-02-01T00:00:00Z",
+        &["GE-Proton9-4.tar.gz", "GE-Proton9-4.sha512sum"],
+    );
+
+    Mock::given(method("GET"))
+        .and(path(
+            "/repos/owner/private-proton/releases/tags/GE-Proton9-4",
+        ))
+        .and(header("authorization", "Bearer secret-token"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&release))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/assets/0"))
+        .and(header("authorization", "Bearer secret-token"))
+        .and(header("accept", "application/octet-stream"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.clone()))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/assets/1"))
+        .and(header("authorization", "Bearer secret-token"))
+        .and(header("accept", "application/octet-stream"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(format!("{}  GE-Proton9-4.tar.gz\n", sha512(&archive))),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let config = ConfigModule {
+        owner: "owner".to_string(),
+        repo: "private-proton".to_string(),
+        api_base: Some(server.uri()),
+        token_command: Some("echo secret-token".to_string()),
+        ..test_config(dir.path())
+    };
+    let mut manager = new_manager(&config);
+
+    manager.install_release("GE-Proton9-4").await.unwrap();
+    assert!(config.install_dir.join("GE-Proton9-4/proton").is_file());
+
+    let config = ConfigModule {
+        token_command: None,
+        ..config
+    };
+    let manager = new_manager(&config);
+    let _ = manager.get_release("GE-Proton9-4").await;
+    let requests = server.received_requests().await.unwrap();
+    let request = requests.last().unwrap();
+    assert_eq!(header_value(request, "authorization"), None);
+}
+
+/// A GitHub module pointed at `server`.
+fn github_config(server: &MockServer, dir: &Path) -> ConfigModule {
+    ConfigModule {
+        owner: "owner".to_string(),
+        repo: "proton".to_string(),
+        api_base: Some(server.uri()),
+        ..test_config(dir)
+    }
+}
+
+#[test]
+async fn test_github_conditional_requests() {
+    let server = MockServer::start().await;
+    let release = github_release(&server, "GE-Proton9-5", "2024-03-01T00:00:00Z", &[]);
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/proton/releases"))
+        .and(header("if-none-match", "\"v1\""))
+        .respond_with(ResponseTemplate::new(304))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/proton/releases"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("etag", "\"v1\"")
+                .set_body_json(json!([release])),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&github_config(&server, dir.path()));
+
+    for _ in 0..2 {
+        let releases = manager.fetch_releases(10).await.unwrap();
+        assert_eq!(releases[0].tag_name, "GE-Proton9-5");
+    }
+}
+
+#[test]
+async fn test_github_rate_limited() {
+    let server = MockServer::start().await;
+    let reset = chrono::Utc::now().timestamp() + 3600;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/proton/releases/tags/GE-Proton9-6"))
+        .respond_with(
+            ResponseTemplate::new(403)
+                .insert_header("x-ratelimit-remaining", "0")
+                .insert_header("x-ratelimit-reset", reset.to_string().as_str()),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let manager = new_manager(&github_config(&server, dir.path()));
+
+    let error = manager.get_release("GE-Proton9-6").await.unwrap_err();
+    assert!(format!("{:?}", error).starts_with("Rate limit exceeded"));
+}
+
+#[test]
+async fn test_github_release_stream_follows_links() {
+    let server = MockServer::start().await;
+    let first_page = json!([
+        github_release(&server, "GE-Proton9-9", "2024-06-03T00:00:00Z", &[]),
+        github_release(&server, "GE-Proton9-8", "2024-06-02T00:00:00Z", &[]),
+    ]);
+    let second_page = json!([github_release(
+        &server,
+        "GE-Proton9-7",
+        "2024-06-01T00:00:00Z",
+        &[]
+    )]);
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/proton/releases"))
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(second_page))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/proton/releases"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header(
+                    "link",
+                    format!(
+                        "<{0}/repos/owner/proton/releases?per_page=2&page=2>; rel=\"next\", \
+                         <{0}/repos/owner/proton/releases?per_page=2&page=2>; rel=\"last\"",
+                        server.uri()
+                    )
+                    .as_str(),
+                )
+                .set_body_json(first_page),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&github_config(&server, dir.path()));
+
+    let mut tags = Vec::new();
+    let mut stream = manager.stream_releases(2, 1);
+    while let Some(release) = stream.next().await.unwrap() {
+        tags.push(release.tag_name);
+    }
+
+    assert_eq!(tags, vec!["GE-Proton9-9", "GE-Proton9-8", "GE-Proton9-7"]);
+}
+
+#[test]
+async fn test_resolve_version_constraints() {
+    let server = MockServer::start().await;
+    let releases = json!([
+        github_release(&server, "GE-Proton9-9", "2024-06-03T00:00:00Z", &[]),
+        github_release(&server, "GE-Proton9-10", "2024-06-02T00:00:00Z", &[]),
+        github_release(&server, "GE-Proton8-26", "2024-01-02T00:00:00Z", &[]),
+        github_release(&server, "GE-Proton8-25", "2024-01-01T00:00:00Z", &[]),
+        github_release(&server, "Proton-9.0", "2023-12-01T00:00:00Z", &[]),
+        github_release(&server, "7.0rc6-GE-1", "2022-03-01T00:00:00Z", &[]),
+    ]);
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/proton/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(releases))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&github_config(&server, dir.path()));
+
+    let cases = [
+        ("latest", "GE-Proton9-10"),
+        ("previous", "GE-Proton9-9"),
+        ("GE-Proton8", "GE-Proton8-26"),
+        ("GE-Proton9-*", "GE-Proton9-10"),
+        ("<GE-Proton8-26", "GE-Proton8-25"),
+        // Only releases with the same prefix are compared.
+        ("<GE-Proton9-9", "GE-Proton8-26"),
+        ("<=Proton-9.0", "Proton-9.0"),
+    ];
+    for (constraint, expected) in cases {
+        let release = manager.resolve_release(constraint).await.unwrap();
+        assert_eq!(release.tag_name, expected, "resolving {}", constraint);
+    }
+
+    assert!(manager.resolve_release(">=GE-Proton10-1").await.is_err());
+    assert!(manager.resolve_release("<GE-Proton8").await.is_err());
+
+    let mut tags = [
+        "6.21-GE-2",
+        "GE-Proton9-10",
+        "7.0rc6-GE-1",
+        "7.0-GE-1",
+        "GE-Proton9-9",
+    ]
+    .map(Version::parse);
+    tags.sort();
+    let tags = tags.map(|v| v.numbers);
+    assert_eq!(
+        tags,
+        [
+            vec![6, 21, 2],
+            vec![7, 0, 1],
+            vec![7, 0, 1],
+            vec![9, 9],
+            vec![9, 10]
+        ]
+    );
+    assert!(Version::parse("7.0rc6-GE-1") < Version::parse("7.0-GE-1"));
+}
+
+#[test]
+async fn test_release_channels() {
+    let server = MockServer::start().await;
+    let mut prerelease = github_release(&server, "GE-Proton9-12", "2024-07-02T00:00:00Z", &[]);
+    prerelease["prerelease"] = json!(true);
+    let mut draft = github_release(&server, "GE-Proton9-13", "2024-07-03T00:00:00Z", &[]);
+    draft["draft"] = json!(true);
+    let releases = json!([
+        draft,
+        prerelease,
+        github_release(&server, "GE-Proton9-11", "2024-07-01T00:00:00Z", &[]),
+    ]);
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/proton/releases"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(releases))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let cases = [
+        (Channel::Stable, "GE-Proton9-11"),
+        (Channel::Prerelease, "GE-Proton9-12"),
+        (Channel::Any, "GE-Proton9-13"),
+    ];
+    for (channel, expected) in cases {
+        let mut manager = new_manager(&github_config(&server, dir.path()));
+        manager.config.channel = channel;
+
+        let latest = manager.resolve_release("latest").await.unwrap();
+        assert_eq!(latest.tag_name, expected);
+
+        let releases = manager.fetch_releases(10).await.unwrap();
+        assert_eq!(releases[0].tag_name, expected);
+    }
+}
+
+#[test]
+async fn test_asset_selection_rules() {
+    let server = MockServer::start().await;
+    let archive = make_archive("wine-9.0-amd64");
+    let release = github_release(
+        &server,
+        "wine-9.0",
+        "2024-08-01T00:00:00Z",
+        &[
+            "wine-9.0-amd64.tar.gz",
+            "wine-9.0-amd64-debug.tar.gz",
+            "wine-9.0-x86.tar.gz",
+            "wine-9.0.sha512sum",
+        ],
+    );
+
+    Mock::given(method("GET"))
+        .and(path("/repos/owner/proton/releases/tags/wine-9.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(&release))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/download/wine-9.0/wine-9.0-amd64.tar.gz"))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.clone()))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/download/wine-9.0/wine-9.0.sha512sum"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(format!("{}  wine-9.0-amd64.tar.gz\n", sha512(&archive))),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&github_config(&server, dir.path()));
+
+    let error = manager.install_release("wine-9.0").await.unwrap_err();
+    let message = format!("{:?}", error);
+    for candidate in [
+        "wine-9.0-amd64.tar.gz",
+        "wine-9.0-amd64-debug.tar.gz",
+        "wine-9.0-x86.tar.gz",
+    ] {
+        assert!(message.contains(candidate), "{}", message);
+    }
+
+    manager.config.asset_pattern = Some("re:-amd64".to_string());
+    manager.config.exclude_patterns = vec!["*-debug.*".to_string()];
+    manager.install_release("wine-9.0").await.unwrap();
+    assert!(manager
+        .config
+        .install_dir
+        .join("wine-9.0-amd64/proton")
+        .is_file());
+}
+
+#[test]
+async fn test_checksum_sources() {
+    let server = MockServer::start().await;
+    let archive = make_archive("wine-9.0-rc1-amd64");
+    let sha256 = hash(&archive, checksums::Algorithm::SHA2256).to_lowercase();
+
+    let aggregate = github_release(
+        &server,
+        "wine-9.0-rc1",
+        "2024-09-01T00:00:00Z",
+        &["wine-9.0-rc1-amd64.tar.gz", "SHA256SUMS"],
+    );
+    let mut in_body = github_release(
+        &server,
+        "wine-9.0-rc2",
+        "2024-09-02T00:00:00Z",
+        &["wine-9.0-rc1-amd64.tar.gz"],
+    );
+    in_body["body"] = json!(format!(
+        "Checksums:\n```\n{}  wine-9.0-rc1-amd64.tar.gz\n```",
+        sha256
+    ));
+    let missing = github_release(
+        &server,
+        "wine-9.0-rc3",
+        "2024-09-03T00:00:00Z",
+        &["wine-9.0-rc1-amd64.tar.gz", "checksums.txt"],
+    );
+
+    for release in [&aggregate, &in_body, &missing] {
+        let tag = release["tag_name"].as_str().unwrap();
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/proton/releases/tags/{}", tag)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(release))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/download/{}/wine-9.0-rc1-amd64.tar.gz", tag)))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.clone()))
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/download/wine-9.0-rc1/SHA256SUMS"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "{}  wine-9.0-rc1-x86.tar.gz\nSHA256 (wine-9.0-rc1-amd64.tar.gz) = {}\n",
+            "0".repeat(64),
+            sha256
+        )))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/download/wine-9.0-rc3/checksums.txt"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_string(format!("{} *wine-9.0-rc1-x86.tar.gz\n", "0".repeat(64))),
+        )
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&github_config(&server, dir.path()));
+
+    for tag in ["wine-9.0-rc1", "wine-9.0-rc2"] {
+        manager.install_release(tag).await.unwrap();
+        std::fs::remove_file(manager.config.cache_dir.join("wine-9.0-rc1-amd64.tar.gz")).unwrap();
+    }
+
+    let error = manager.install_release("wine-9.0-rc3").await.unwrap_err();
+    assert_eq!(
+        format!("{:?}", error),
+        "Not found: No checksum for wine-9.0-rc1-amd64.tar.gz in checksums.txt"
+    );
+}
+
+/// Signs `data` like `minisign -S`, returning the public key and the `.minisig` file.
+fn minisign(data: &[u8], seed: u8) -> (String, String) {
+    use base64::Engine;
+    use blake2::Digest;
+    use ed25519_dalek::Signer;
+
+    let engine = base64::engine::general_purpose::STANDARD;
+    let key = ed25519_dalek::SigningKey::from_bytes(&[seed; 32]);
+    let key_id = [seed; 8];
+    let public_key =
+        engine.encode([b"Ed".as_slice(), &key_id, key.verifying_key().as_bytes()].concat());
+
+    let signature = key.sign(&blake2::Blake2b512::digest(data)).to_bytes();
+    let trusted_comment = "timestamp:0";
+    let global_signature = key
+        .sign(&[signature.as_slice(), trusted_comment.as_bytes()].concat())
+        .to_bytes();
+
+    let file = format!(
+        "untrusted comment: signature from test key\n{}\ntrusted comment: {}\n{}\n",
+        engine.encode([b"ED".as_slice(), &key_id, &signature].concat()),
+        trusted_comment,
+        engine.encode(global_signature)
+    );
+    (public_key, file)
+}
+
+#[test]
+async fn test_minisign_signatures() {
+    let server = MockServer::start().await;
+    let archive = make_archive("GE-Proton9-20");
+    let (public_key, signature) = minisign(&archive, 1);
+    let (untrusted_key, _) = minisign(&archive, 2);
+
+    let signed = github_release(
+        &server,
+        "GE-Proton9-20",
+        "2024-10-01T00:00:00Z",
+        &[
+            "GE-Proton9-20.tar.gz",
+            "GE-Proton9-20.sha512sum",
+            "GE-Proton9-20.tar.gz.minisig",
+        ],
+    );
+    let unsigned = github_release(
+        &server,
+        "GE-Proton9-21",
+        "2024-10-02T00:00:00Z",
+        &["GE-Proton9-21.tar.gz", "GE-Proton9-21.sha512sum"],
+    );
+    let unsigned_archive = make_archive("GE-Proton9-21");
+
+    for (release, archive) in [(&signed, &archive), (&unsigned, &unsigned_archive)] {
+        let tag = release["tag_name"].as_str().unwrap();
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/proton/releases/tags/{}", tag)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(release))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/download/{0}/{0}.tar.gz", tag)))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.clone()))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/download/{0}/{0}.sha512sum", tag)))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+                "{}  {}.tar.gz\n",
+                sha512(archive),
+                tag
+            )))
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("GET"))
+        .and(path("/download/GE-Proton9-20/GE-Proton9-20.tar.gz.minisig"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(signature))
+        .mount(&server)
+        .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&github_config(&server, dir.path()));
+
+    manager.config.minisign_keys = vec![untrusted_key];
+    let error = manager.install_release("GE-Proton9-20").await.unwrap_err();
+    assert_eq!(
+        format!("{:?}", error),
+        "Invalid signature: GE-Proton9-20.tar.gz.minisig: signed by an untrusted key"
+    );
+    assert!(!manager
+        .config
+        .cache_dir
+        .join("GE-Proton9-20.tar.gz")
+        .exists());
+
+    manager.config.minisign_keys = vec![public_key];
+    manager.install_release("GE-Proton9-20").await.unwrap();
+    assert!(manager
+        .config
+        .install_dir
+        .join("GE-Proton9-20/proton")
+        .is_file());
+
+    let error = manager.install_release("GE-Proton9-21").await.unwrap_err();
+    assert_eq!(
+        format!("{:?}", error),
+        "No signature found for GE-Proton9-21.tar.gz"
+    );
+}
+
+/// Generates an OpenPGP key for `user` in the keyring at `home`, returning the armored public key
+/// file.
+fn pgp_key(home: &Path, user: &str) -> std::path::PathBuf {
+    let status = std::process::Command::new("gpg")
+        .arg("--homedir")
+        .arg(home)
+        .args(["--batch", "--passphrase", "", "--quick-gen-key", user])
+        .args(["ed25519", "sign", "never"])
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success());
+
+    let output = std::process::Command::new("gpg")
+        .arg("--homedir")
+        .arg(home)
+        .args(["--armor", "--export", user])
+        .output()
+        .unwrap();
+    let key = home.join(format!("{}.asc", user));
+    std::fs::write(&key, output.stdout).unwrap();
+    key
+}
+
+/// Makes an armored detached signature of `data` by `user`'s key in the keyring at `home`.
+fn pgp_sign(home: &Path, user: &str, data: &[u8]) -> Vec<u8> {
+    let file = home.join("signed");
+    std::fs::write(&file, data).unwrap();
+    let output = std::process::Command::new("gpg")
+        .arg("--homedir")
+        .arg(home)
+        .args(["--batch", "--local-user", user, "--armor", "--detach-sign"])
+        .args(["--output", "-"])
+        .arg(&file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    output.stdout
+}
+
+#[test]
+async fn test_openpgp_signatures() {
+    let server = MockServer::start().await;
+    let home = tempfile::tempdir().unwrap();
+    std::fs::set_permissions(
+        home.path(),
+        std::os::unix::fs::PermissionsExt::from_mode(0o700),
+    )
+    .unwrap();
+    let trusted_key = pgp_key(home.path(), "trusted@example.com");
+    let untrusted_key = pgp_key(home.path(), "untrusted@example.com");
+
+    // One release signed with both kinds of signature, and one signed only with minisign.
+    let archives = [
+        ("GE-Proton9-22", make_archive("GE-Proton9-22"), "asc"),
+        ("GE-Proton9-23", make_archive("GE-Proton9-23"), "minisig"),
+    ];
+    for (tag, archive, kind) in &archives {
+        let archive_name = format!("{}.tar.gz", tag);
+        let checksum_name = format!("{}.sha512sum", tag);
+        let mut assets = vec![
+            archive_name.clone(),
+            checksum_name.clone(),
+            format!("{}.minisig", archive_name),
+        ];
+        if *kind == "asc" {
+            assets.push(format!("{}.asc", archive_name));
+        }
+        let assets: Vec<&str> = assets.iter().map(String::as_str).collect();
+        let release = github_release(&server, tag, "2024-10-01T00:00:00Z", &assets);
+
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/owner/proton/releases/tags/{}", tag)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(release))
+            .mount(&server)
+            .await;
+        let (_, minisig) = minisign(archive, 1);
+        for (name, body) in [
+            (archive_name.clone(), archive.clone()),
+            (
+                checksum_name,
+                format!("{}  {}\n", sha512(archive), archive_name).into_bytes(),
+            ),
+            (format!("{}.minisig", archive_name), minisig.into_bytes()),
+            (
+                format!("{}.asc", archive_name),
+                pgp_sign(home.path(), "trusted@example.com", archive),
+            ),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(format!("/download/{}/{}", tag, name)))
+                .respond_with(ResponseTemplate::new(200).set_body_bytes(body))
+                .mount(&server)
+                .await;
+        }
+    }
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&github_config(&server, dir.path()));
+    let cache_dir = manager.config.cache_dir.clone();
+
+    manager.config.pgp_keys = vec![untrusted_key];
+    let error = manager.install_release("GE-Proton9-22").await.unwrap_err();
+    assert!(
+        format!("{:?}", error).starts_with("Invalid signature: GE-Proton9-22.tar.gz.asc"),
+        "{:?}",
+        error
+    );
+    assert!(!cache_dir.join("GE-Proton9-22.tar.gz.part").exists());
+
+    manager.config.pgp_keys = vec![trusted_key];
+    manager.install_release("GE-Proton9-22").await.unwrap();
+    assert!(manager
+        .config
+        .install_dir
+        .join("GE-Proton9-22/proton")
+        .is_file());
+
+    // A signature no trusted key can check is missing, not invalid, and the download is kept.
+    let error = manager.install_release("GE-Proton9-23").await.unwrap_err();
+    assert_eq!(
+        format!("{:?}", error),
+        "No signature found for GE-Proton9-23.tar.gz"
+    );
+    assert!(cache_dir.join("GE-Proton9-23.tar.gz.part").is_file());
+
+    let _ = std::process::Command::new("gpgconf")
+        .arg("--homedir")
+        .arg(home.path())
+        .args(["--kill", "gpg-agent"])
+        .status();
+}
+
+/// Serves release `tag` with a single archive containing `top_level`, uploaded at `updated_at`.
+async fn mount_single_archive_release(
+    server: &MockServer,
+    tag: &str,
+    top_level: &str,
+    updated_at: &str,
+) {
+    let archive = make_archive(top_level);
+    let release = github_release(
+        server,
+        tag,
+        updated_at,
+        &[&format!("{}.tar.gz", tag), &format!("{}.sha512sum", tag)],
+    );
+
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/owner/proton/releases/tags/{}", tag)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(release))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/download/{0}/{0}.tar.gz", tag)))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.clone()))
+        .mount(server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/download/{0}/{0}.sha512sum", tag)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "{}  {}.tar.gz\n",
+            sha512(&archive),
+            tag
+        )))
+        .mount(server)
+        .await;
+}
+
+#[test]
+async fn test_pinned_asset_hashes() {
+    let server = MockServer::start().await;
+    mount_single_archive_release(
+        &server,
+        "GE-Proton9-40",
+        "GE-Proton9-40",
+        "2024-11-01T00:00:00Z",
+    )
+    .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = github_config(&server, dir.path());
+    config.pinning = Pinning::Refuse;
+    let mut manager = new_manager(&config);
+    manager.install_release("GE-Proton9-40").await.unwrap();
+    manager
+        .remove_release("GE-Proton9-40", false)
+        .await
+        .unwrap();
+
+    // The same tag is re-uploaded with different contents.
+    server.reset().await;
+    mount_single_archive_release(
+        &server,
+        "GE-Proton9-40",
+        "GE-Proton9-40-fixed",
+        "2024-11-02T00:00:00Z",
+    )
+    .await;
+
+    let error = manager.install_release("GE-Proton9-40").await.unwrap_err();
+    assert!(format!("{:?}", error).starts_with(
+        "GE-Proton9-40.tar.gz of @owner/proton GE-Proton9-40 changed since it was first installed"
+    ));
+    assert!(!config.cache_dir.join("GE-Proton9-40.tar.gz").exists());
+
+    config.pinning = Pinning::Warn;
+    let mut manager = new_manager(&config);
+    manager.install_release("GE-Proton9-40").await.unwrap();
+    assert!(config
+        .install_dir
+        .join("GE-Proton9-40-fixed/proton")
+        .is_file());
+}
+
+#[test]
+async fn test_pin_survives_checksum_algorithm_change() {
+    let server = MockServer::start().await;
+    let tag = "GE-Proton9-41";
+    let published_at = "2024-11-01T00:00:00Z";
+    mount_single_archive_release(&server, tag, tag, published_at).await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = github_config(&server, dir.path());
+    config.pinning = Pinning::Refuse;
+    let mut manager = new_manager(&config);
+    manager.install_release(tag).await.unwrap();
+    manager.remove_release(tag, false).await.unwrap();
+
+    // Different contents with a sha256sum instead of a sha512sum, and the same upload date.
+    server.reset().await;
+    let archive = make_archive("GE-Proton9-41-fixed");
+    let release = github_release(
+        &server,
+        tag,
+        published_at,
+        &[&format!("{}.tar.gz", tag), &format!("{}.sha256sum", tag)],
+    );
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/owner/proton/releases/tags/{}", tag)))
+        .respond_with(ResponseTemplate::new(200).set_body_json(release))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/download/{0}/{0}.tar.gz", tag)))
+        .respond_with(ResponseTemplate::new(200).set_body_bytes(archive.clone()))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(format!("/download/{0}/{0}.sha256sum", tag)))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            "{}  {}.tar.gz\n",
+            hash(&archive, checksums::Algorithm::SHA2256).to_lowercase(),
+            tag
+        )))
+        .mount(&server)
+        .await;
+
+    let error = format!("{:?}", manager.install_release(tag).await.unwrap_err());
+    assert!(
+        error.contains("changed since it was first installed: pinned SHA2512"),
+        "{}",
+        error
+    );
+    assert!(!config.install_dir.join("GE-Proton9-41-fixed").exists());
+}
+
+#[test]
+async fn test_resume_partial_download() {
+    let server = MockServer::start().await;
+    let archive = make_archive("GE-Proton9-50");
+    let resume_from = archive.len() / 2;
+
+    Mock::given(method("GET"))
+        .and(path("/download/GE-Proton9-50/GE-Proton9-50.tar.gz"))
+        .and(header("range", format!("bytes={}-", resume_from).as_str()))
+        .respond_with(
+            ResponseTemplate::new(206)
+                .insert_header(
+                    "content-range",
+                    format!(
+                        "bytes {}-{}/{}",
+                        resume_from,
+                        archive.len() - 1,
+                        archive.len()
+                    )
+                    .as_str(),
+                )
+                .set_body_bytes(archive[resume_from..].to_vec()),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    mount_single_archive_release(
+        &server,
+        "GE-Proton9-50",
+        "GE-Proton9-50",
+        "2024-12-01T00:00:00Z",
+    )
+    .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&github_config(&server, dir.path()));
+    let cache_dir = manager.config.cache_dir.clone();
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    std::fs::write(
+        cache_dir.join("GE-Proton9-50.tar.gz.part"),
+        &archive[..resume_from],
+    )
+    .unwrap();
+
+    manager.install_release("GE-Proton9-50").await.unwrap();
+    assert!(manager
+        .config
+        .install_dir
+        .join("GE-Proton9-50/proton")
+        .is_file());
+    assert_eq!(
+        std::fs::read(cache_dir.join("GE-Proton9-50.tar.gz")).unwrap(),
+        archive
+    );
+    assert!(!cache_dir.join("GE-Proton9-50.tar.gz.part").exists());
+}
+
+#[test]
+async fn test_progress_events() {
+    let server = MockServer::start().await;
+    mount_single_archive_release(
+        &server,
+        "GE-Proton9-60",
+        "GE-Proton9-60",
+        "2025-01-01T00:00:00Z",
+    )
+    .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&github_config(&server, dir.path()));
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    manager.set_progress_sink(channel_sink(sender));
+
+    manager.install_release("GE-Proton9-60").await.unwrap();
+
+    let archive_size = make_archive("GE-Proton9-60").len() as u64;
+    let mut finished = Vec::new();
+    while let Ok(event) = receiver.try_recv() {
+        assert!(event.total.is_none_or(|total| event.bytes <= total));
+        if event.done {
+            assert_eq!(event.total, Some(archive_size));
+            assert_eq!(event.bytes, archive_size);
+            finished.push(event.phase);
+        }
+    }
+    assert_eq!(
+        finished,
+        [
+            ProgressPhase::Download,
+            ProgressPhase::Verify,
+            ProgressPhase::Extract
+        ]
+    );
+}
+
+/// Fails the first `failures` requests for `route` with `response`, before the mocks already
+/// mounted for it answer.
+async fn mount_failures(
+    server: &MockServer,
+    route: &str,
+    response: ResponseTemplate,
+    failures: u64,
+) {
+    Mock::given(method("GET"))
+        .and(path(route))
+        .respond_with(response)
+        .up_to_n_times(failures)
+        .with_priority(1)
+        .mount(server)
+        .await;
+}
+
+#[test]
+async fn test_network_retries() {
+    let server = MockServer::start().await;
+    let tag = "GE-Proton9-70";
+    mount_single_archive_release(&server, tag, tag, "2025-02-01T00:00:00Z").await;
+    mount_failures(
+        &server,
+        "/repos/owner/proton/releases/tags/GE-Proton9-70",
+        ResponseTemplate::new(502),
+        2,
+    )
+    .await;
+    mount_failures(
+        &server,
+        "/download/GE-Proton9-70/GE-Proton9-70.sha512sum",
+        ResponseTemplate::new(429).insert_header("retry-after", "1"),
+        1,
+    )
+    .await;
+    mount_failures(
+        &server,
+        "/download/GE-Proton9-70/GE-Proton9-70.tar.gz",
+        ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(2)),
+        1,
+    )
+    .await;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = github_config(&server, dir.path());
+    config.network.read_timeout = 0.5;
+    config.network.retry_delay = 0.01;
+    let mut manager = new_manager(&config);
+
+    let started = std::time::Instant::now();
+    manager.install_release(tag).await.unwrap();
+    assert!(started.elapsed() >= std::time::Duration::from_secs(1));
+    assert!(config.install_dir.join(tag).join("proton").exists());
+
+    // Once the retries run out, the last error is returned.
+    mount_failures(
+        &server,
+        "/repos/owner/proton/releases/tags/GE-Proton9-71",
+        ResponseTemplate::new(503),
+        u64::MAX,
+    )
+    .await;
+    config.network.retries = 2;
+    let mut manager = new_manager(&config);
+    let error = manager.install_release("GE-Proton9-71").await.unwrap_err();
+    assert!(format!("{:?}", error).contains("503"), "{:?}", error);
+
+    let requests = server.received_requests().await.unwrap();
+    let count = |route: &str| requests.iter().filter(|r| r.url.path() == route).count();
+    assert_eq!(count("/repos/owner/proton/releases/tags/GE-Proton9-70"), 3);
+    assert_eq!(count("/download/GE-Proton9-70/GE-Proton9-70.sha512sum"), 2);
+    assert_eq!(count("/download/GE-Proton9-70/GE-Proton9-70.tar.gz"), 2);
+    assert_eq!(count("/repos/owner/proton/releases/tags/GE-Proton9-71"), 3);
+}
+
+/// A module whose settings are all valid, for tests to break one of them.
+fn valid_config(dir: &Path) -> ConfigModule {
+    ConfigModule {
+        owner: "owner".to_string(),
+        repo: "repo".to_string(),
+        ..test_config(dir)
+    }
+}
+
+#[test]
+async fn test_invalid_source_config() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(ProtonManager::new("test".to_string(), &valid_config(dir.path())).is_ok());
+
+    let invalid = [
+        ConfigModule {
+            api_base: Some("not a url".to_string()),
+            ..valid_config(dir.path())
+        },
+        ConfigModule {
+            repo: String::new(),
+            ..valid_config(dir.path())
+        },
+        ConfigModule {
+            source: SourceType::GitLab,
+            owner: String::new(),
+            ..valid_config(dir.path())
+        },
+        ConfigModule {
+            source: SourceType::GitLab,
+            project: Some(String::new()),
+            ..valid_config(dir.path())
+        },
+        ConfigModule {
+            source: SourceType::Gitea,
+            repo: String::new(),
+            ..valid_config(dir.path())
+        },
+        ConfigModule {
+            source: SourceType::Gitea,
+            host: Some("not a url".to_string()),
+            ..valid_config(dir.path())
+        },
+        ConfigModule {
+            source: SourceType::Http,
+            ..valid_config(dir.path())
+        },
+        ConfigModule {
+            source: SourceType::Http,
+            host: Some("not a url".to_string()),
+            ..valid_config(dir.path())
+        },
+        ConfigModule {
+            source: SourceType::Local,
+            ..valid_config(dir.path())
+        },
+    ];
+
+    for config in invalid {
+        let manager = ProtonManager::new("test".to_string(), &config);
+        assert!(manager.is_err(), "{:?}", config);
+    }
+}
+
+#[test]
+async fn test_invalid_network_config() {
+    let dir = tempfile::tempdir().unwrap();
+    let invalid = [
+        NetworkConfig {
+            https_proxy: Some("not a proxy".to_string()),
+            ..Default::default()
+        },
+        NetworkConfig {
+            ca_bundle: Some(dir.path().join("missing.pem")),
+            ..Default::default()
+        },
+    ];
+
+    for network in invalid {
+        let config = ConfigModule {
+            network: network.clone(),
+            ..valid_config(dir.path())
+        };
+        let manager = ProtonManager::new("test".to_string(), &config);
+        assert!(manager.is_err(), "{:?}", network);
+    }
+}
+
+/// Runs the `pup` binary with `config` as its config file, keeping everything it writes in `dir`.
+async fn run_pup(dir: &Path, config: &str, args: &[&str]) -> (Option<i32>, String) {
+    let config_path = dir.join("config.toml");
+    std::fs::write(
+        &config_path,
+        format!(
+            "[test]\ninstall_dir = \"{0}/install\"\ncache_dir = \"{0}/cache\"\n{1}",
+            dir.display(),
+            config
+        ),
+    )
+    .unwrap();
+
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_pup"))
+        .arg("--config")
+        .arg(&config_path)
+        .args(args)
+        .env("XDG_CACHE_HOME", dir)
+        .output()
+        .await
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+    (output.status.code(), stderr)
+}
+
+#[test]
+async fn test_misconfigured_module_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let (code, stderr) = run_pup(
+        dir.path(),
+        "[network]\nhttp_proxy = \"::not a url::\"\n",
+        &["list"],
+    )
+    .await;
+
+    assert_eq!(code, Some(1), "{}", stderr);
+    assert!(
+        stderr.contains("Module test is misconfigured"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+async fn test_network_config() {
+    let config = Config::new(Option::from("tests/config.test.toml".to_string()));
+
+    assert!(!config.modules.contains_key("network"));
+    assert_eq!(config.network.read_timeout, 60.0);
+    assert_eq!(config.network.retries, 5);
+    assert_eq!(config.network.connect_timeout, 10.0);
+    assert_eq!(config.modules["test"].network, config.network);
+}
+
+fn header_value(request: &wiremock::Request, name: &str) -> Option<String> {
+    let name = wiremock::http::HeaderName::from_bytes(name.as_bytes().to_vec()).unwrap();
+    request
+        .headers
+        .get(&name)
+        .map(|values| values.last().as_str().to_string())
+}
+
+#[test]
+async fn test_proxy_and_user_agent() {
+    let server = MockServer::start().await;
+    let tag = "GE-Proton9-80";
+    mount_single_archive_release(&server, tag, tag, "2025-03-01T00:00:00Z").await;
+
+    // The API host only resolves through the proxy, while downloads from the mock server itself
+    // bypass it.
+    let dir = tempfile::tempdir().unwrap();
+    let mut config = github_config(&server, dir.path());
+    config.api_base = Some("http://api.github.invalid".to_string());
+    config.network.http_proxy = Some(format!("http://user:secret@{}", server.address()));
+    config.network.no_proxy = Some("127.0.0.1".to_string());
+    config.network.user_agent = Some("pup-tests/1.0".to_string());
+    let mut manager = new_manager(&config);
+
+    manager.install_release(tag).await.unwrap();
+
+    let requests = server.received_requests().await.unwrap();
+    assert!(requests
+        .iter()
+        .all(|r| header_value(r, "user-agent").as_deref() == Some("pup-tests/1.0")));
+    let (proxied, direct): (Vec<_>, Vec<_>) = requests
+        .iter()
+        .partition(|r| r.url.host_str() == Some("api.github.invalid"));
+    assert_eq!(proxied.len(), 1);
+    assert_eq!(
+        header_value(proxied[0], "proxy-authorization").as_deref(),
+        Some("Basic dXNlcjpzZWNyZXQ=")
+    );
+    assert_eq!(direct.len(), 2);
+    assert!(direct
+        .iter()
+        .all(|r| header_value(r, "proxy-authorization").is_none()));
+}
+
+/// Builds a `.tar.gz` from raw entries, bypassing the path checks of `tar::Builder` so that
+/// malicious archives can be made. Each entry is a path, a type, a link target and contents.
+fn make_raw_archive(entries: &[(&str, tar::EntryType, &str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::fast()));
+    for (name, entry_type, link, contents) in entries {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.as_old_mut().linkname[..link.len()].copy_from_slice(link.as_bytes());
+        header.set_entry_type(*entry_type);
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append(&header, *contents).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Adds `archive` and its checksum to the folder read by the local source, returning the folder.
+fn add_local_archive(dir: &Path, filename: &str, archive: &[u8]) -> std::path::PathBuf {
+    let local_dir = dir.join("archives");
+    std::fs::create_dir_all(&local_dir).unwrap();
+    std::fs::write(
+        local_dir.join(format!("{}.sha512sum", filename)),
+        format!("{}  {}\n", sha512(archive), filename),
+    )
+    .unwrap();
+    std::fs::write(local_dir.join(filename), archive).unwrap();
+    local_dir
+}
+
+/// A local module installing from a folder holding `archive` as `filename`.
+fn local_archive_config(dir: &Path, filename: &str, archive: &[u8]) -> ConfigModule {
+    ConfigModule {
+        source: SourceType::Local,
+        local_dir: Some(add_local_archive(dir, filename, archive)),
+        ..test_config(dir)
+    }
+}
+
+#[test]
+async fn test_unsafe_archive_entries() {
+    use tar::EntryType::{Directory, Link, Regular, Symlink};
+
+    let dir = tempfile::tempdir().unwrap();
+    let absolute = dir.path().join("absolute");
+    let absolute = absolute.to_str().unwrap();
+    let archive = make_raw_archive(&[
+        ("GE-Proton9-90/", Directory, "", b""),
+        ("GE-Proton9-90/proton", Regular, "", b"#!/bin/sh\n"),
+        ("GE-Proton9-90/files", Symlink, "proton", b""),
+        ("GE-Proton9-90/../../escaped", Regular, "", b"x"),
+        (absolute, Regular, "", b"x"),
+        ("GE-Proton9-90/up", Symlink, "../../..", b""),
+        ("GE-Proton9-90/passwd", Link, "/etc/passwd", b""),
+        ("GE-Proton9-90/dot", Symlink, ".", b""),
+        ("GE-Proton9-90/dot/sneaky", Symlink, "../../outside", b""),
+        // Only outside once `dot` is followed rather than cancelled out by the `..` after it.
+        (
+            "GE-Proton9-90/chained",
+            Symlink,
+            "dot/dot/dot/../../../..",
+            b"",
+        ),
+        // `missing` could be made a link by a later entry.
+        ("GE-Proton9-90/later", Symlink, "missing/..", b""),
+        ("GE-Proton9-90/inside", Symlink, "dot/files", b""),
+    ]);
+    let mut manager = new_manager(&local_archive_config(
+        dir.path(),
+        "GE-Proton9-90.tar.gz",
+        &archive,
+    ));
+    let install_dir = manager.config.install_dir.clone();
+
+    let error = format!(
+        "{:?}",
+        manager.install_release("GE-Proton9-90").await.unwrap_err()
+    );
+    for path in [
+        "GE-Proton9-90/../../escaped",
+        absolute,
+        "GE-Proton9-90/up",
+        "GE-Proton9-90/passwd",
+        "GE-Proton9-90/dot/sneaky",
+        "GE-Proton9-90/chained",
+        "GE-Proton9-90/later",
+    ] {
+        assert!(error.contains(path), "{} not in {}", path, error);
+    }
+    assert!(!error.contains("GE-Proton9-90/files"));
+    assert!(!error.contains("GE-Proton9-90/inside"));
+    assert!(!dir.path().join("escaped").exists());
+    assert!(!Path::new(absolute).exists());
+    assert_eq!(std::fs::read_dir(&install_dir).unwrap().count(), 0);
+    assert!(manager.get_installed_releases().await.unwrap().is_empty());
+}
+
+#[test]
+async fn test_archive_limits() {
+    use tar::EntryType::{Directory, Regular, Symlink};
+
+    let dir = tempfile::tempdir().unwrap();
+    let archive = make_raw_archive(&[
+        ("GE-Proton9-91/", Directory, "", b""),
+        ("GE-Proton9-91/proton", Regular, "", b"#!/bin/sh\n"),
+        ("GE-Proton9-91/files/", Directory, "", b""),
+        ("GE-Proton9-91/files/lib", Symlink, "../proton", b""),
+    ]);
+    let mut manager = new_manager(&local_archive_config(
+        dir.path(),
+        "GE-Proton9-91.tar.gz",
+        &archive,
+    ));
+    let install_dir = manager.config.install_dir.clone();
+
+    let mut config = manager.config.clone();
+    config.max_extract_files = Some(3);
+    let mut limited = new_manager(&config);
+    let error = limited.install_release("GE-Proton9-91").await.unwrap_err();
+    assert!(format!("{:?}", error).contains("more than 3 files"));
+    assert_eq!(std::fs::read_dir(&install_dir).unwrap().count(), 0);
+
+    config.max_extract_files = None;
+    config.max_extract_size = Some(4);
+    let mut limited = new_manager(&config);
+    let error = limited.install_release("GE-Proton9-91").await.unwrap_err();
+    assert!(format!("{:?}", error).contains("more than 4 bytes"));
+    assert_eq!(std::fs::read_dir(&install_dir).unwrap().count(), 0);
+
+    // Links that stay inside the tool are fine.
+    manager.install_release("GE-Proton9-91").await.unwrap();
+    let link = install_dir.join("GE-Proton9-91/files/lib");
+    assert!(link.is_symlink());
+    assert_eq!(std::fs::read(link).unwrap(), b"#!/bin/sh\n");
+}
+
+#[test]
+async fn test_staged_install() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = make_archive("GE-Proton9-92");
+    let mut manager = new_manager(&local_archive_config(
+        dir.path(),
+        "GE-Proton9-92.tar.gz",
+        &archive,
+    ));
+    let previous = manager.config.install_dir.join("GE-Proton9-92");
+    std::fs::create_dir_all(&previous).unwrap();
+    std::fs::write(previous.join("stale"), "from an older build").unwrap();
+    let staging_dirs = || {
+        std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|e| {
+                let name = e.as_ref().unwrap().file_name();
+                !["archives", "cache", "install"]
+                    .map(OsString::from)
+                    .contains(&name)
+            })
+            .count()
+    };
+
+    // A tool that fails validation leaves the previous build as it was.
+    let mut config = manager.config.clone();
+    config.required_files = Some(vec!["compatibilitytool.vdf".to_string()]);
+    let mut strict = new_manager(&config);
+    let error = strict.install_release("GE-Proton9-92").await.unwrap_err();
+    assert!(format!("{:?}", error).contains("GE-Proton9-92/compatibilitytool.vdf"));
+    assert!(previous.join("stale").is_file());
+    assert!(!previous.join("proton").exists());
+    assert_eq!(staging_dirs(), 0);
+
+    // Otherwise the previous build is replaced as a whole.
+    manager.install_release("GE-Proton9-92").await.unwrap();
+    assert!(previous.join("proton").is_file());
+    assert!(!previous.join("stale").exists());
+    assert_eq!(staging_dirs(), 0);
+}
+
+/// A tool directory with an executable, a file in a subdirectory and a symlink to it.
+fn make_tarball(top_level: &str) -> Vec<u8> {
+    let mut builder = tar::Builder::new(Vec::new());
+    for (path, entry_type, mode, contents) in [
+        ("", tar::EntryType::Directory, 0o755, &b""[..]),
+        ("proton", tar::EntryType::Regular, 0o755, b"#!/bin/sh\n"),
+        ("files/lib.so", tar::EntryType::Regular, 0o644, b"lib"),
+    ] {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(contents.len() as u64);
+        header.set_mode(mode);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, Path::new(top_level).join(path), contents)
+            .unwrap();
+    }
+
+    let mut header = tar::Header::new_gnu();
+    header.set_entry_type(tar::EntryType::Symlink);
+    header.set_size(0);
+    header.set_cksum();
+    builder
+        .append_link(&mut header, format!("{}/lib", top_level), "files/lib.so")
+        .unwrap();
+    builder.into_inner().unwrap()
+}
+
+fn make_zip(entries: &[(&str, &[u8])], top_level: &str) -> Vec<u8> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let options = zip::write::FileOptions::default();
+    zip.add_directory(top_level, options).unwrap();
+    zip.start_file(
+        format!("{}/proton", top_level),
+        options.unix_permissions(0o755),
+    )
+    .unwrap();
+    zip.write_all(b"#!/bin/sh\n").unwrap();
+    zip.start_file(format!("{}/files/lib.so", top_level), options)
+        .unwrap();
+    zip.write_all(b"lib").unwrap();
+    zip.add_symlink(format!("{}/lib", top_level), "files/lib.so", options)
+        .unwrap();
+    for (name, contents) in entries {
+        zip.start_file(*name, options).unwrap();
+        zip.write_all(contents).unwrap();
+    }
+    zip.finish().unwrap().into_inner()
+}
+
+#[test]
+async fn test_archive_formats() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut bzip2 = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+    bzip2.write_all(&make_tarball("GE-Proton9-95")).unwrap();
+    let archives = [
+        (
+            "GE-Proton9-94.tar.zst",
+            zstd::encode_all(&make_tarball("GE-Proton9-94")[..], 0).unwrap(),
+        ),
+        ("GE-Proton9-95.tar.bz2", bzip2.finish().unwrap()),
+        ("GE-Proton9-96.zip", make_zip(&[], "GE-Proton9-96")),
+        // Named as a gzipped tarball, but compressed with zstd.
+        (
+            "GE-Proton9-97.tar.gz",
+            zstd::encode_all(&make_tarball("GE-Proton9-97")[..], 0).unwrap(),
+        ),
+    ];
+
+    for (filename, archive) in archives {
+        let tag = &filename[..13];
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = new_manager(&local_archive_config(dir.path(), filename, &archive));
+        let tool_dir = manager.config.install_dir.join(tag);
+
+        manager.install_release(tag).await.unwrap();
+
+        let mode = std::fs::metadata(tool_dir.join("proton"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755, "{}", filename);
+        assert_eq!(
+            std::fs::read_link(tool_dir.join("lib")).unwrap(),
+            Path::new("files/lib.so"),
+            "{}",
+            filename
+        );
+        assert_eq!(
+            std::fs::read(tool_dir.join("lib")).unwrap(),
+            b"lib",
+            "{}",
+            filename
+        );
+        let release = manager.get_installed_releases().await.unwrap().remove(0);
+        assert_eq!(release.installed_contents, [tag], "{}", filename);
+    }
+}
+
+#[test]
+async fn test_unsafe_zip_entries() {
+    let dir = tempfile::tempdir().unwrap();
+    let archive = make_zip(
+        &[("../escaped", b"x"), ("/tmp/pup-zip-absolute", b"x")],
+        "GE-Proton9-98",
+    );
+    let mut manager = new_manager(&local_archive_config(
+        dir.path(),
+        "GE-Proton9-98.zip",
+        &archive,
+    ));
+    let install_dir = manager.config.install_dir.clone();
+
+    let error = format!(
+        "{:?}",
+        manager.install_release("GE-Proton9-98").await.unwrap_err()
+    );
+    assert!(error.contains("../escaped (contains `..`)"), "{}", error);
+    assert!(error.contains("/tmp/pup-zip-absolute (is an absolute path)"));
+    assert!(!dir.path().join("escaped").exists());
+    assert!(!install_dir.join("GE-Proton9-98").exists());
+}
+
+#[test]
+async fn test_parallel_extraction() {
+    let tag = "GE-Proton9-99";
+    let mut builder = tar::Builder::new(Vec::new());
+    let mut append = |path: String, entry_type, link: &str, contents: &[u8]| {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        match entry_type {
+            tar::EntryType::Regular => builder.append_data(&mut header, path, contents),
+            _ => builder.append_link(&mut header, path, link),
+        }
+        .unwrap();
+    };
+    for i in 0..300 {
+        let path = format!("{}/files/{}/{}.dll", tag, i % 7, i);
+        append(
+            path,
+            tar::EntryType::Regular,
+            "",
+            format!("dll {}", i).as_bytes(),
+        );
+    }
+    let large = vec![7; 6 * 1024 * 1024];
+    append(
+        format!("{}/large", tag),
+        tar::EntryType::Regular,
+        "",
+        &large,
+    );
+    // Entries that depend on earlier ones: a second copy of a file, and links to files.
+    let replaced = format!("{}/files/0/0.dll", tag);
+    append(replaced.clone(), tar::EntryType::Regular, "", b"replaced");
+    append(
+        format!("{}/hardlink", tag),
+        tar::EntryType::Link,
+        &replaced,
+        b"",
+    );
+    append(
+        format!("{}/symlink", tag),
+        tar::EntryType::Symlink,
+        "large",
+        b"",
+    );
+    let tarball = builder.into_inner().unwrap();
+
+    // Small blocks, so that the xz decoder has several to decompress in parallel.
+    let stream = liblzma::stream::MtStreamBuilder::new()
+        .threads(4)
+        .block_size(64 * 1024)
+        .preset(0)
+        .encoder()
+        .unwrap();
+    let mut encoder = liblzma::write::XzEncoder::new_stream(Vec::new(), stream);
+    encoder.write_all(&tarball).unwrap();
+    let archive = encoder.finish().unwrap();
+
+    for threads in [1, 8] {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manager = new_manager(&local_archive_config(
+            dir.path(),
+            &format!("{}.tar.xz", tag),
+            &archive,
+        ));
+        manager.config.extract_threads = Some(threads);
+        let tool_dir = manager.config.install_dir.join(tag);
+
+        manager.install_release(tag).await.unwrap();
+
+        for i in 1..300 {
+            let path = tool_dir.join(format!("files/{}/{}.dll", i % 7, i));
+            assert_eq!(
+                std::fs::read(path).unwrap(),
+                format!("dll {}", i).as_bytes()
+            );
+        }
+        let read = |path: &str| std::fs::read(tool_dir.join(path)).unwrap();
+        assert_eq!(read("files/0/0.dll"), b"replaced", "{} threads", threads);
+        assert_eq!(read("hardlink"), b"replaced", "{} threads", threads);
+        assert_eq!(read("symlink"), large, "{} threads", threads);
+    }
+}
+
+#[test]
+async fn test_remove_releases() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&local_archive_config(
+        dir.path(),
+        "GE-Proton9-1.tar.gz",
+        &make_archive("GE-Proton9-1"),
+    ));
+    for tag in ["GE-Proton9-2", "GE-Proton9-3"] {
+        add_local_archive(dir.path(), &format!("{}.tar.gz", tag), &make_archive(tag));
+    }
+    let install_dir = manager.config.install_dir.clone();
+    let cache_dir = manager.config.cache_dir.clone();
+    for tag in ["GE-Proton9-1", "GE-Proton9-2", "GE-Proton9-3"] {
+        manager.install_release(tag).await.unwrap();
+    }
+
+    manager.remove_release("GE-Proton9-3", false).await.unwrap();
+    assert!(!install_dir.join("GE-Proton9-3").exists());
+    assert!(!cache_dir.join("GE-Proton9-3.tar.gz").exists());
+    let error = manager.remove_release("GE-Proton9-3", false).await;
+    assert!(format!("{:?}", error.unwrap_err()).contains("GE-Proton9-3"));
+
+    // A newer tool that pup did not install is neither kept as the latest nor removed.
+    let unmanaged = install_dir.join("GE-Proton9-3");
+    std::fs::create_dir_all(&unmanaged).unwrap();
+    manager.remove_all_but_latest(true).await.unwrap();
+    assert!(unmanaged.is_dir());
+    assert!(install_dir.join("GE-Proton9-2/proton").is_file());
+    assert!(!install_dir.join("GE-Proton9-1").exists());
+    assert!(cache_dir.join("GE-Proton9-1.tar.gz").is_file());
+
+    let installed: Vec<String> = manager
+        .get_installed_releases()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.tag_name)
+        .collect();
+    assert_eq!(installed, ["GE-Proton9-3", "GE-Proton9-2"]);
+}
+
+#[test]
+async fn test_old_install_outlives_release_cache() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = local_archive_config(
+        dir.path(),
+        "GE-Proton9-1.tar.gz",
+        &make_archive("GE-Proton9-1"),
+    );
+    for i in 2..=105 {
+        add_local_archive(dir.path(), &format!("GE-Proton9-{}.tar.gz", i), b"");
+    }
+    let mut manager = new_manager(&config);
+    let mut stream = manager.stream_releases(100, 1);
+    while stream.next().await.unwrap().is_some() {}
+
+    // Older than the 100 releases the cache keeps, so only kept because it is installed.
+    manager.install_release("GE-Proton9-1").await.unwrap();
+
+    let mut manager = new_manager(&config);
+    let installed: Vec<String> = manager
+        .get_installed_releases()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.tag_name)
+        .collect();
+    assert_eq!(installed, ["GE-Proton9-1"]);
+    let tools = manager.scan_install_dir(false).await.unwrap();
+    assert_eq!(tools[0].state, InstallState::Managed);
+
+    manager.remove_release("GE-Proton9-1", false).await.unwrap();
+    assert!(!config.install_dir.join("GE-Proton9-1").exists());
+}
+
+#[test]
+async fn test_scan_install_dir() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut manager = new_manager(&local_archive_config(
+        dir.path(),
+        "GE-Proton9-1.tar.gz",
+        &make_archive("GE-Proton9-1"),
+    ));
+    add_local_archive(
+        dir.path(),
+        "GE-Proton9-2.tar.gz",
+        &make_archive("GE-Proton9-2"),
+    );
+    let install_dir = manager.config.install_dir.clone();
+    manager.install_release("GE-Proton9-1").await.unwrap();
+    manager.fetch_releases(10).await.unwrap();
+
+    let renamed = install_dir.join("renamed");
+    std::fs::create_dir_all(&renamed).unwrap();
+    std::fs::write(renamed.join("version"), "1704067200 GE-Proton9-2\n").unwrap();
+    std::fs::create_dir_all(install_dir.join("lutris-GE-Proton9-2-x86_64")).unwrap();
+    std::fs::create_dir_all(install_dir.join("SteamTinkerLaunch")).unwrap();
+    std::fs::create_dir_all(install_dir.join("GE-Proton9-4")).unwrap();
+    std::fs::write(install_dir.join("notes.txt"), "").unwrap();
+    std::os::unix::fs::symlink("GE-Proton9-1", install_dir.join("proton-latest")).unwrap();
+    // Published after the releases were cached.
+    add_local_archive(
+        dir.path(),
+        "GE-Proton9-4.tar.gz",
+        &make_archive("GE-Proton9-4"),
+    );
+
+    let classify = |tools: Vec<pup_rs::models::installed::InstalledTool>| {
+        tools
+            .into_iter()
+            .map(|tool| {
+                let dir_name = tool.path.file_name().unwrap().to_str().unwrap().to_string();
+                (dir_name, tool.name, tool.state)
+            })
+            .collect::<Vec<_>>()
+    };
+    let expected = |latest_state| {
+        vec![
+            (
+                "GE-Proton9-1".to_string(),
+                "GE-Proton9-1".to_string(),
+                InstallState::Managed,
+            ),
+            (
+                "GE-Proton9-4".to_string(),
+                "GE-Proton9-4".to_string(),
+                latest_state,
+            ),
+            (
+                "SteamTinkerLaunch".to_string(),
+                "SteamTinkerLaunch".to_string(),
+                InstallState::Unknown,
+            ),
+            (
+                "lutris-GE-Proton9-2-x86_64".to_string(),
+                "GE-Proton9-2".to_string(),
+                InstallState::Unmanaged,
+            ),
+            (
+                "renamed".to_string(),
+                "GE-Proton9-2".to_string(),
+                InstallState::Unmanaged,
+            ),
+        ]
+    };
+
+    // Only cached releases are matched unless fetching is asked for.
+    let tools = classify(manager.scan_install_dir(false).await.unwrap());
+    assert_eq!(tools, expected(InstallState::Unknown));
+    let tools = classify(manager.scan_install_dir(true).await.unwrap());
+    assert_eq!(tools, expected(InstallState::Unmanaged));
+
+    // A tool deleted by hand is no longer reported as installed.
+    std::fs::remove_dir_all(install_dir.join("GE-Proton9-1")).unwrap();
+    let tools = classify(manager.scan_install_dir(false).await.unwrap());
+    assert!(tools
+        .iter()
+        .all(|(dir_name, _, _)| dir_name != "GE-Proton9-1"));
+    let releases = manager.fetch_releases(10).await.unwrap();
+    let release = releases.iter().find(|r| r.tag_name == "GE-Proton9-1");
+    assert!(release.unwrap().installed_in.is_none());
+}
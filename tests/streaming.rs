@@ -0,0 +1,105 @@
+//! Checks that archives are extracted as a stream rather than read into memory. This is a test
+//! binary of its own because it measures every allocation the process makes, so no other test may
+//! run alongside it.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::path::Path;
+use std::sync::atomic;
+use std::sync::atomic::AtomicUsize;
+
+use pup_rs::config::{ConfigModule, SourceType};
+use pup_rs::proton_manager::ProtonManager;
+use tokio::test;
+
+/// Records the largest allocation made since it was last reset.
+struct TrackingAllocator;
+
+static LARGEST_ALLOCATION: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        LARGEST_ALLOCATION.fetch_max(layout.size(), atomic::Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        LARGEST_ALLOCATION.fetch_max(new_size, atomic::Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: TrackingAllocator = TrackingAllocator;
+
+#[test]
+async fn test_streaming_extraction() {
+    std::env::set_var(
+        "XDG_CACHE_HOME",
+        Path::new(env!("CARGO_TARGET_TMPDIR")).join("cache"),
+    );
+
+    let size = 64 * 1024 * 1024;
+    let mut builder = tar::Builder::new(liblzma::write::XzEncoder::new(Vec::new(), 0));
+    for (path, contents_size) in [("GE-Proton9-93/files/large", size), ("LICENSE", 3)] {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents_size);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(
+                &mut header,
+                path,
+                std::io::Read::take(std::io::repeat(0), contents_size),
+            )
+            .unwrap();
+    }
+    let archive = builder.into_inner().unwrap().finish().unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let local_dir = dir.path().join("archives");
+    std::fs::create_dir_all(&local_dir).unwrap();
+    let archive_path = local_dir.join("GE-Proton9-93.tar.xz");
+    std::fs::write(&archive_path, archive).unwrap();
+    std::fs::write(
+        local_dir.join("GE-Proton9-93.tar.xz.sha512sum"),
+        format!(
+            "{}  GE-Proton9-93.tar.xz\n",
+            checksums::hash_file(&archive_path, checksums::Algorithm::SHA2512)
+        ),
+    )
+    .unwrap();
+    let config = ConfigModule {
+        install_dir: dir.path().join("install"),
+        cache_dir: dir.path().join("cache"),
+        source: SourceType::Local,
+        local_dir: Some(local_dir),
+        ..Default::default()
+    };
+    std::fs::create_dir_all(&config.cache_dir).unwrap();
+    let name = dir
+        .path()
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_string();
+    let mut manager = ProtonManager::new(name, &config).unwrap();
+
+    LARGEST_ALLOCATION.store(0, atomic::Ordering::Relaxed);
+    manager.install_release("GE-Proton9-93").await.unwrap();
+    let largest = LARGEST_ALLOCATION.load(atomic::Ordering::Relaxed);
+
+    let large = config.install_dir.join("GE-Proton9-93/files/large");
+    assert_eq!(std::fs::metadata(large).unwrap().len(), size);
+    assert!(
+        largest < 16 * 1024 * 1024,
+        "allocated {} bytes at once",
+        largest
+    );
+    let release = manager.get_installed_releases().await.unwrap().remove(0);
+    assert_eq!(release.installed_contents, ["GE-Proton9-93", "LICENSE"]);
+}